@@ -0,0 +1,134 @@
+//! Compares catr's plain copy with the per-record loop plain runs went
+//! through before it, on a large file written both to a pipe and to another
+//! file:
+//!
+//!     cargo bench -p catr
+//!
+//! The file is 2 GiB unless `CATR_BENCH_MB` says otherwise. The plain copy
+//! only exists on unix.
+
+#[cfg(unix)]
+fn main() -> std::io::Result<()> {
+    unix::main()
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("the plain copy is only built on unix; nothing to compare");
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        env,
+        fs::File,
+        io::{self, BufWriter, Read, Write},
+        os::fd::OwnedFd,
+        path::Path,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use catr::{run_plain, Config};
+    use common::Records;
+
+    const MB: u64 = 1024 * 1024;
+
+    pub fn main() -> io::Result<()> {
+        let mb = env::var("CATR_BENCH_MB")
+            .ok()
+            .and_then(|mb| mb.parse().ok())
+            .unwrap_or(2048);
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("big.txt");
+        let output = dir.path().join("out.txt");
+        write_input(&input, mb)?;
+
+        let files = [input.to_string_lossy().into_owned()];
+        let config = Config::builder()
+            .files(&files)
+            .build()
+            .expect("plain config");
+        let size = mb * MB;
+        println!("{mb} MiB of text, best of 3");
+        report("record loop, to a pipe", size, || {
+            to_pipe(size, |out| record_loop(&files, out))
+        })?;
+        report("plain copy, to a pipe", size, || {
+            to_pipe(size, |out| {
+                run_plain(&config, &mut File::from(OwnedFd::from(out)))
+            })
+        })?;
+        report("record loop, to a file", size, || {
+            record_loop(&files, File::create(&output)?)
+        })?;
+        report("plain copy, to a file", size, || {
+            let mut out = File::create(&output)?;
+            run_plain(&config, &mut out).map_err(io::Error::other)
+        })?;
+        Ok(())
+    }
+
+    /// The loop catr ran for every file before the plain copy: each line is
+    /// read into a buffer and written through a `BufWriter`.
+    fn record_loop(files: &[String], out: impl Write) -> io::Result<()> {
+        let mut out = BufWriter::new(out);
+        let mut line = Vec::new();
+        for file in files {
+            let mut records = Records::new(common::open(file)?, b'\n');
+            while records.read(&mut line)? {
+                out.write_all(&line)?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Fills `path` with `mb` MiB of lines of text.
+    fn write_input(path: &Path, mb: u64) -> io::Result<()> {
+        let line = b"The quick brown fox jumps over the lazy dog, 0123456789.\n";
+        let chunk: Vec<u8> = line.iter().copied().cycle().take(MB as usize).collect();
+        let mut file = BufWriter::new(File::create(path)?);
+        for _ in 0..mb {
+            file.write_all(&chunk)?;
+        }
+        file.flush()
+    }
+
+    /// Runs `copy` with the write end of a pipe whose other end is drained by
+    /// another thread, and checks all `size` bytes came through.
+    fn to_pipe<E>(size: u64, copy: impl FnOnce(io::PipeWriter) -> Result<(), E>) -> io::Result<()>
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let (mut reader, writer) = io::pipe()?;
+        let drain = thread::spawn(move || -> io::Result<u64> {
+            let mut buf = vec![0; 128 * 1024];
+            let mut total = 0;
+            loop {
+                match reader.read(&mut buf)? {
+                    0 => return Ok(total),
+                    n => total += n as u64,
+                }
+            }
+        });
+        copy(writer).map_err(io::Error::other)?;
+        let total = drain.join().expect("drain thread")?;
+        assert_eq!(total, size);
+        Ok(())
+    }
+
+    fn report(name: &str, size: u64, mut run: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+        let mut best = Duration::MAX;
+        for _ in 0..3 {
+            let start = Instant::now();
+            run()?;
+            best = best.min(start.elapsed());
+        }
+        let secs = best.as_secs_f64();
+        println!(
+            "{name:<24} {secs:>7.3} s {:>8.0} MiB/s",
+            (size / MB) as f64 / secs
+        );
+        Ok(())
+    }
+}
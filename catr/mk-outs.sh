@@ -11,20 +11,66 @@ EMPTY="$ROOT/empty.txt"
 FOX="$ROOT/fox.txt"
 SPIDERS="$ROOT/spiders.txt"
 BUSTLE="$ROOT/the-bustle.txt"
+LATIN1="$ROOT/latin1.txt"
+CTRL="$ROOT/ctrl.txt"
+BLANKS="$ROOT/blanks.txt"
 ALL="$EMPTY $FOX $SPIDERS $BUSTLE"
 
-for FILE in $ALL; do
+for FILE in $ALL $LATIN1; do
     BASENAME=$(basename "$FILE")
     cat    $FILE > ${OUT_DIR}/${BASENAME}.out
     cat -n $FILE > ${OUT_DIR}/${BASENAME}.n.out
     cat -b $FILE > ${OUT_DIR}/${BASENAME}.b.out
 done
 
+for FILE in $CTRL $LATIN1; do
+    BASENAME=$(basename "$FILE")
+    for FLAG in v E T A; do
+        cat -$FLAG $FILE > ${OUT_DIR}/${BASENAME}.${FLAG}.out
+    done
+done
+cat -A -n $CTRL > ${OUT_DIR}/$(basename $CTRL).An.out
+
 cat    $ALL > $OUT_DIR/all.out
 cat -n $ALL > $OUT_DIR/all.n.out
 cat -b $ALL > $OUT_DIR/all.b.out
 
+for FILE in $ALL; do
+    cat -n $FILE
+done > $OUT_DIR/all.n.per-file.out
+
+cat -s  $BLANKS $BLANKS > $OUT_DIR/blanks.txt.s.out
+cat -sn $BLANKS $BLANKS > $OUT_DIR/blanks.txt.sn.out
+cat -sb $BLANKS $BLANKS > $OUT_DIR/blanks.txt.sb.out
+
 cat    < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).stdin.out
 cat -n < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).n.stdin.out
 cat -b < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).b.stdin.out
 
+
+# --lines and --from/--to pick lines of each file but keep the numbers
+# cat -n and -b give them.
+awk 'FNR >= 2 && FNR <= 4' $ALL > $OUT_DIR/all.lines.out
+awk 'FNR >= 2 && FNR <= 4 { printf "%6d\t%s\n", NR, $0 }' $ALL > $OUT_DIR/all.lines.n.out
+awk '$0 != "" { n++ }
+     FNR >= 4 { if ($0 != "") printf "%6d\t%s\n", n, $0; else print }' \
+    $ALL > $OUT_DIR/all.lines.b.out
+
+ERRORS="$ROOT/errors.txt"
+sed_range='function out() { if (n) printf "%6d\t%s\n", NR, $0; else print }
+    FNR == 1 { inside = 0 }
+    inside { out(); if ($0 ~ to) inside = 0; next }
+    $0 ~ from { inside = 1; out() }'
+awk -v from=ERROR -v to='^$' "$sed_range" $ERRORS > $OUT_DIR/errors.txt.from-to.out
+awk -v from=ERROR -v to='^$' -v n=1 "$sed_range" $ERRORS $ERRORS \
+    > $OUT_DIR/errors.txt.from-to.n.out
+
+# Encodings, checked against iconv. The UTF-16 file has a byte order mark
+# and CRLF line ends.
+UTF16="$ROOT/utf16le.txt"
+CP1252="$ROOT/cp1252.txt"
+iconv -f UTF-16LE -t UTF-8 $UTF16 > $OUT_DIR/utf16le.txt.utf8.out
+iconv -f UTF-16 -t UTF-8 $UTF16 | sed 's/\r$//' | cat -n > $OUT_DIR/utf16le.txt.strip.n.out
+iconv -f WINDOWS-1252 -t UTF-8 $CP1252 > $OUT_DIR/cp1252.txt.utf8.out
+iconv -f WINDOWS-1252 -t UTF-16LE $CP1252 > $OUT_DIR/cp1252.txt.utf16le.out
+iconv -f UTF-16LE -t WINDOWS-1252//TRANSLIT $UTF16 > $OUT_DIR/utf16le.txt.cp1252.out
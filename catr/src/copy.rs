@@ -0,0 +1,135 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use common::{Error, Result};
+
+/// The size of the buffer used when the kernel cannot do the copying, as in
+/// GNU cat.
+pub(crate) const BUF_SIZE: usize = 128 * 1024;
+
+/// Copies what is left of `input` to `out`, starting with whatever it has
+/// buffered.
+///
+/// On Linux the rest is moved by `copy_file_range`, `sendfile` or `splice`,
+/// whichever the two files allow, without passing through user space.
+/// Anything the kernel will not copy, including the rest of a copy it gave
+/// up on, goes through `buf`.
+pub(crate) fn copy(
+    filename: &str,
+    input: &mut BufReader<File>,
+    out: &mut File,
+    buf: &mut [u8],
+) -> Result<()> {
+    let len = input.buffer().len();
+    out.write_all(input.buffer())?;
+    input.consume(len);
+
+    #[cfg(target_os = "linux")]
+    if linux::copy(input.get_ref(), out) {
+        return Ok(());
+    }
+    buffered(filename, input.get_mut(), out, buf)
+}
+
+/// Copies `input` to `out` through `buf`.
+pub(crate) fn buffered(
+    filename: &str,
+    input: &mut impl Read,
+    out: &mut impl Write,
+    buf: &mut [u8],
+) -> Result<()> {
+    loop {
+        match input.read(buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => out.write_all(&buf[..n])?,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(Error::read(filename, e)),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{fs::File, io, os::fd::AsRawFd, ptr};
+
+    /// The most asked of one call; the kernel moves a little under 2 GiB at
+    /// a time at most anyway.
+    const CHUNK: usize = 1 << 30;
+
+    /// Copies `input` to `out` up to end of file, trying each way the kernel
+    /// has of doing it in turn. Returns false if none of them finished, with
+    /// the file offsets left wherever the last one stopped.
+    pub fn copy(input: &File, out: &File) -> bool {
+        let (input, out) = (input.as_raw_fd(), out.as_raw_fd());
+        // SAFETY: both descriptors stay open for the calls, and null offsets
+        // make the kernel use and advance the files' own.
+        let ways: [&dyn Fn() -> isize; 3] = [
+            &|| unsafe {
+                libc::copy_file_range(input, ptr::null_mut(), out, ptr::null_mut(), CHUNK, 0)
+            },
+            &|| unsafe { libc::sendfile(out, input, ptr::null_mut(), CHUNK) },
+            &|| unsafe { libc::splice(input, ptr::null_mut(), out, ptr::null_mut(), CHUNK, 0) },
+        ];
+        ways.iter().any(|way| drain(*way))
+    }
+
+    /// Calls `way` until it reaches end of file. Reaching it at once does
+    /// not count, since files in /proc and /sys claim to be empty this way.
+    fn drain(way: &dyn Fn() -> isize) -> bool {
+        let mut copied = false;
+        loop {
+            match way() {
+                0 => return copied,
+                n if n > 0 => copied = true,
+                _ if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                _ => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{copy, BUF_SIZE};
+    use std::{
+        fs::{self, File, OpenOptions},
+        io::{BufRead, BufReader, Write},
+    };
+
+    fn input(dir: &tempfile::TempDir, data: &[u8]) -> BufReader<File> {
+        let path = dir.path().join("in");
+        fs::write(&path, data).unwrap();
+        BufReader::with_capacity(4, File::open(path).unwrap())
+    }
+
+    #[test]
+    fn test_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let data: Vec<u8> = (0..3 * BUF_SIZE).map(|i| i as u8).collect();
+        let mut buf = vec![0; BUF_SIZE];
+
+        let out_path = dir.path().join("out");
+        let mut out = File::create(&out_path).unwrap();
+        let mut reader = input(&dir, &data);
+        reader.fill_buf().unwrap();
+        copy("in", &mut reader, &mut out, &mut buf).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), data);
+
+        // The kernel will not copy onto a file opened for appending.
+        let mut out = OpenOptions::new().append(true).open(&out_path).unwrap();
+        out.write_all(b"--").unwrap();
+        copy("in", &mut input(&dir, b"tail"), &mut out, &mut buf).unwrap();
+        assert!(fs::read(&out_path).unwrap().ends_with(b"--tail"));
+    }
+
+    #[test]
+    fn test_copy_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let out_path = dir.path().join("out");
+        let mut out = File::create(&out_path).unwrap();
+        copy("in", &mut input(&dir, b""), &mut out, &mut [0; 16]).unwrap();
+        assert_eq!(fs::read(&out_path).unwrap(), b"");
+    }
+}
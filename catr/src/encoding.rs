@@ -0,0 +1,291 @@
+use std::{
+    io::{self, BufRead, BufReader, Cursor, Read, Write},
+    str,
+};
+
+use encoding_rs::{CoderResult, Decoder, Encoder, EncoderResult, Encoding, UTF_16BE, UTF_16LE};
+
+/// The longest byte order mark, that of UTF-8.
+const BOM_LEN: usize = 3;
+
+/// How much decoded text is held at once.
+const DECODE_SIZE: usize = 8 * 1024;
+
+/// Looks an encoding up by one of its WHATWG labels, such as "utf-16le" or
+/// "windows-1252".
+pub fn parse(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label_no_replacement(label.as_bytes())
+        .ok_or_else(|| format!("unknown encoding '{label}'"))
+}
+
+/// Readies `reader` to be split into lines. With `from`, its text is
+/// decoded to UTF-8 from that encoding, or from the one its byte order mark
+/// names. The byte order mark is kept, converted with the rest, unless
+/// `strip_bom`.
+pub(crate) fn decode(
+    mut reader: Box<dyn BufRead>,
+    from: Option<&'static Encoding>,
+    strip_bom: bool,
+) -> io::Result<Box<dyn BufRead>> {
+    if from.is_none() && !strip_bom {
+        return Ok(reader);
+    }
+    let mut start = Vec::with_capacity(BOM_LEN);
+    reader
+        .by_ref()
+        .take(BOM_LEN as u64)
+        .read_to_end(&mut start)?;
+    let bom = Encoding::for_bom(&start);
+    let rest = Cursor::new(start.split_off(bom.map_or(0, |(_, len)| len)));
+    let rest = rest.chain(reader);
+    let Some(from) = from else {
+        return Ok(Box::new(rest));
+    };
+
+    let mut text = Vec::with_capacity(DECODE_SIZE);
+    if bom.is_some() && !strip_bom {
+        text.extend("\u{feff}".as_bytes());
+    }
+    Ok(Box::new(BufReader::new(Decode {
+        reader: rest,
+        decoder: bom
+            .map_or(from, |(encoding, _)| encoding)
+            .new_decoder_without_bom_handling(),
+        text,
+        pos: 0,
+        done: false,
+    })))
+}
+
+/// Reads text from `reader` as UTF-8. Malformed sequences become U+FFFD.
+struct Decode<R> {
+    reader: R,
+    decoder: Decoder,
+    /// Decoded text, read up to `pos`.
+    text: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Read for Decode<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.text.len() && !self.done {
+            let input = self.reader.fill_buf()?;
+            let last = input.is_empty();
+            self.text.resize(DECODE_SIZE, 0);
+            let (result, read, written, _) =
+                self.decoder.decode_to_utf8(input, &mut self.text, last);
+            self.reader.consume(read);
+            self.text.truncate(written);
+            self.pos = 0;
+            self.done = last && result == CoderResult::InputEmpty;
+        }
+        let len = (&self.text[self.pos..]).read(buf)?;
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Writes UTF-8 text onto `inner` in another encoding. Characters the
+/// encoding has no room for are written as `?`.
+pub(crate) struct Encode<W> {
+    inner: W,
+    target: Target,
+    /// The start of a character cut off at the end of the last write.
+    pending: Vec<u8>,
+    encoded: Vec<u8>,
+}
+
+enum Target {
+    Encoder(Encoder),
+    /// encoding_rs decodes UTF-16 but writes UTF-8 in its place.
+    Utf16 {
+        big_endian: bool,
+    },
+}
+
+impl<W: Write> Encode<W> {
+    pub fn new(inner: W, encoding: &'static Encoding) -> Self {
+        let target = if encoding == UTF_16LE || encoding == UTF_16BE {
+            Target::Utf16 {
+                big_endian: encoding == UTF_16BE,
+            }
+        } else {
+            Target::Encoder(encoding.new_encoder())
+        };
+        Self {
+            inner,
+            target,
+            pending: Vec::new(),
+            encoded: Vec::new(),
+        }
+    }
+
+    /// Ends the text, as some encodings need to return to their initial
+    /// state, and flushes `inner`.
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            return Err(not_utf8());
+        }
+        encode(&mut self.target, "", true, &mut self.encoded);
+        self.inner.write_all(&self.encoded)?;
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for Encode<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid = match str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => {
+                self.pending.truncate(self.pending.len() - buf.len());
+                return Err(not_utf8());
+            }
+        };
+        let text = str::from_utf8(&self.pending[..valid]).expect("checked above");
+        encode(&mut self.target, text, false, &mut self.encoded);
+        self.pending.drain(..valid);
+        let written = self.inner.write_all(&self.encoded);
+        self.encoded.clear();
+        written.map(|()| buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn not_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "output is not valid UTF-8")
+}
+
+/// Appends `text` to `out` as `target` encodes it.
+fn encode(target: &mut Target, mut text: &str, last: bool, out: &mut Vec<u8>) {
+    let encoder = match target {
+        Target::Utf16 { big_endian } => {
+            for unit in text.encode_utf16() {
+                out.extend(if *big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+            return;
+        }
+        Target::Encoder(encoder) => encoder,
+    };
+    let reserve = |encoder: &Encoder, out: &mut Vec<u8>, len: usize| {
+        let room = encoder
+            .max_buffer_length_from_utf8_without_replacement(len)
+            .expect("text fits in memory");
+        out.reserve(room);
+    };
+    loop {
+        reserve(encoder, out, text.len());
+        let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(text, out, last);
+        text = &text[read..];
+        match result {
+            EncoderResult::InputEmpty => return,
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => {
+                reserve(encoder, out, 1);
+                let (replaced, _) =
+                    encoder.encode_from_utf8_to_vec_without_replacement("?", out, false);
+                debug_assert_eq!(replaced, EncoderResult::InputEmpty);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, parse, Encode};
+    use encoding_rs::{Encoding, ISO_2022_JP, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+    use std::io::{BufRead, Cursor, Read, Write};
+
+    fn read(input: &'static [u8], from: Option<&'static Encoding>, strip_bom: bool) -> Vec<u8> {
+        let reader: Box<dyn BufRead> = Box::new(Cursor::new(input));
+        let mut out = Vec::new();
+        decode(reader, from, strip_bom)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    fn write(text: &[&[u8]], encoding: &'static Encoding) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encode = Encode::new(&mut out, encoding);
+        for bytes in text {
+            encode.write_all(bytes).unwrap();
+        }
+        encode.finish().unwrap();
+        out
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("UTF-16LE"), Ok(UTF_16LE));
+        assert_eq!(parse("cp1252"), Ok(WINDOWS_1252));
+        assert!(parse("utf-9").is_err());
+        assert!(parse("iso-2022-kr").is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let utf16 = b"\xff\xfea\x00\xe9\x00\n\x00";
+        assert_eq!(
+            read(utf16, Some(UTF_8), false),
+            "\u{feff}a\u{e9}\n".as_bytes()
+        );
+        assert_eq!(
+            read(utf16, Some(WINDOWS_1252), true),
+            "a\u{e9}\n".as_bytes()
+        );
+        assert_eq!(
+            read(b"caf\xe9", Some(WINDOWS_1252), false),
+            "caf\u{e9}".as_bytes()
+        );
+        assert_eq!(
+            read(b"caf\xe9", Some(UTF_8), false),
+            "caf\u{fffd}".as_bytes()
+        );
+        assert_eq!(read(b"\xef\xbb", Some(UTF_8), true), "\u{fffd}".as_bytes());
+
+        // Without an encoding, only the byte order mark is touched.
+        assert_eq!(read(b"\xef\xbb\xbfcaf\xe9", None, true), b"caf\xe9");
+        assert_eq!(read(utf16, None, true), b"a\x00\xe9\x00\n\x00");
+        assert_eq!(read(b"ab", None, true), b"ab");
+        assert_eq!(read(b"\xef\xbb\xbf", None, false), b"\xef\xbb\xbf");
+
+        let long: &'static [u8] = "\u{e9}".repeat(10_000).leak().as_bytes();
+        assert_eq!(read(long, Some(UTF_8), false), long);
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            write(&["a\u{e9}\n".as_bytes()], UTF_16LE),
+            b"a\x00\xe9\x00\n\x00"
+        );
+        assert_eq!(write(&["\u{feff}a".as_bytes()], UTF_16BE), b"\xfe\xff\x00a");
+        assert_eq!(
+            write(&["caf\u{e9} \u{263a}".as_bytes()], WINDOWS_1252),
+            b"caf\xe9 ?"
+        );
+        // A character split across writes.
+        assert_eq!(write(&[b"caf\xc3", b"\xa9"], WINDOWS_1252), b"caf\xe9");
+        // The text is ended back in ASCII.
+        assert_eq!(
+            write(&["\u{3042}".as_bytes()], ISO_2022_JP),
+            b"\x1b$B$\"\x1b(B"
+        );
+
+        let mut out = Vec::new();
+        let mut encode = Encode::new(&mut out, WINDOWS_1252);
+        assert!(encode.write_all(b"a\xffb").is_err());
+        assert!(Encode::new(Vec::new(), UTF_16LE).write_all(b"\xc3").is_ok());
+    }
+}
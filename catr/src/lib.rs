@@ -1,22 +1,245 @@
-use std::{
-    error::Error,
-    fs::File,
-    io::{self, BufRead, BufReader},
-};
+#[cfg(unix)]
+mod copy;
+mod encoding;
+mod select;
+mod show;
 
-use clap::{Arg, ArgAction, Command};
+use std::{env, ffi::OsString, io::Write};
+#[cfg(unix)]
+use std::{fs::File, io, os::fd::AsFd};
 
-type MyResult<T> = Result<T, Box<dyn Error>>;
+use clap::{Arg, ArgAction, Command};
+#[cfg(unix)]
+use common::Plain;
+use common::{Compat, Error, Failures, Inputs, NumberFormat, Records, Result};
+use encoding::Encode;
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+pub use select::LineRange;
+use select::{Selection, Selector};
+use show::Show;
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     number_lines: bool,
     number_nonblank_lines: bool,
+    terminator: u8,
+    show: Show,
+    squeeze_blank: bool,
+    number_per_file: bool,
+    number_format: NumberFormat,
+    selection: Option<Selection>,
+    /// What inputs are decoded from, if they are decoded to UTF-8 at all.
+    from_encoding: Option<&'static Encoding>,
+    /// What output is encoded in, if not UTF-8.
+    to_encoding: Option<&'static Encoding>,
+    strip_bom: bool,
+    crlf_to_lf: bool,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Whether the files are written out exactly as they are.
+    pub fn is_plain(&self) -> bool {
+        !(self.number_lines
+            || self.number_nonblank_lines
+            || self.show.any()
+            || self.squeeze_blank
+            || self.selection.is_some()
+            || self.from_encoding.is_some()
+            || self.strip_bom
+            || self.crlf_to_lf)
+    }
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("catr")
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    number_lines: bool,
+    number_nonblank_lines: bool,
+    zero_terminated: bool,
+    compat: Option<Compat>,
+    show: Show,
+    squeeze_blank: bool,
+    number_per_file: bool,
+    number_format: NumberFormat,
+    lines: Option<LineRange>,
+    from: Option<Regex>,
+    to: Option<Regex>,
+    from_encoding: Option<&'static Encoding>,
+    to_encoding: Option<&'static Encoding>,
+    strip_bom: bool,
+    crlf_to_lf: bool,
+}
+
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn number_lines(mut self, number_lines: bool) -> Self {
+        self.number_lines = number_lines;
+        self
+    }
+
+    pub fn number_nonblank_lines(mut self, number_nonblank_lines: bool) -> Self {
+        self.number_nonblank_lines = number_nonblank_lines;
+        self
+    }
+
+    /// Separate records by NUL instead of newline when numbering them.
+    pub fn zero_terminated(mut self, zero_terminated: bool) -> Self {
+        self.zero_terminated = zero_terminated;
+        self
+    }
+
+    /// With `Bsd` or `Posix`, number lines from 1 in each file, as the BSD
+    /// cats do.
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    /// Show control bytes in `^` notation and bytes above 127 with `M-`,
+    /// except for newline and tab.
+    pub fn show_nonprinting(mut self, show_nonprinting: bool) -> Self {
+        self.show.nonprinting = show_nonprinting;
+        self
+    }
+
+    /// Show `$` at the end of each line.
+    pub fn show_ends(mut self, show_ends: bool) -> Self {
+        self.show.ends = show_ends;
+        self
+    }
+
+    /// Show tabs as `^I`.
+    pub fn show_tabs(mut self, show_tabs: bool) -> Self {
+        self.show.tabs = show_tabs;
+        self
+    }
+
+    /// Write only the first of adjacent blank lines.
+    pub fn squeeze_blank(mut self, squeeze_blank: bool) -> Self {
+        self.squeeze_blank = squeeze_blank;
+        self
+    }
+
+    /// Number lines, and squeeze blank lines, in each file on its own rather
+    /// than across all of them.
+    pub fn number_per_file(mut self, number_per_file: bool) -> Self {
+        self.number_per_file = number_per_file;
+        self
+    }
+
+    /// How `-n` and `-b` write line numbers, `%6d\t` by default.
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// Write only these lines of each file. Line numbers from `-n` and
+    /// `-b` still count the lines left out.
+    pub fn lines(mut self, lines: Option<LineRange>) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// Write only the lines of each file from one matching `from` through
+    /// the next one after it matching `to`, as sed's `/FROM/,/TO/` does.
+    pub fn from(mut self, from: Option<Regex>) -> Self {
+        self.from = from;
+        self
+    }
+
+    /// See [`from`](Self::from). Without it, write lines from the start of
+    /// each file through the first matching `to`.
+    pub fn to(mut self, to: Option<Regex>) -> Self {
+        self.to = to;
+        self
+    }
+
+    /// Decode inputs from this encoding to UTF-8, unless a byte order mark
+    /// says they are in another.
+    pub fn from_encoding(mut self, from_encoding: Option<&'static Encoding>) -> Self {
+        self.from_encoding = from_encoding;
+        self
+    }
+
+    /// Write output in this encoding. Inputs are then decoded, from UTF-8
+    /// unless [`from_encoding`](Self::from_encoding) or a byte order mark
+    /// says otherwise.
+    pub fn to_encoding(mut self, to_encoding: Option<&'static Encoding>) -> Self {
+        self.to_encoding = to_encoding;
+        self
+    }
+
+    /// Drop the byte order mark at the start of each file.
+    pub fn strip_bom(mut self, strip_bom: bool) -> Self {
+        self.strip_bom = strip_bom;
+        self
+    }
+
+    /// Write lines ending in CRLF with just LF.
+    pub fn crlf_to_lf(mut self, crlf_to_lf: bool) -> Self {
+        self.crlf_to_lf = crlf_to_lf;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.number_lines && self.number_nonblank_lines {
+            return Err(Error::Usage(
+                "cannot number all lines and nonblank lines at once".to_string(),
+            ));
+        }
+        let selection = match (self.lines, self.from, self.to) {
+            (None, None, None) => None,
+            (Some(lines), None, None) => Some(Selection::Lines(lines)),
+            (None, from, to) => Some(Selection::Pattern { from, to }),
+            (Some(_), _, _) => {
+                return Err(Error::Usage(
+                    "cannot select by line number and by pattern at once".to_string(),
+                ))
+            }
+        };
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            number_lines: self.number_lines,
+            number_nonblank_lines: self.number_nonblank_lines,
+            terminator: common::terminator(self.zero_terminated),
+            show: self.show,
+            squeeze_blank: self.squeeze_blank,
+            number_per_file: self.number_per_file
+                || matches!(self.compat, Some(Compat::Bsd | Compat::Posix)),
+            number_format: self.number_format,
+            selection,
+            from_encoding: self.from_encoding.or(self.to_encoding.and(Some(UTF_8))),
+            to_encoding: self.to_encoding.filter(|&encoding| encoding != UTF_8),
+            strip_bom: self.strip_bom,
+            crlf_to_lf: self.crlf_to_lf,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("catr")
         .version("0.1.0")
         .author("Talentaa <talentaa@qq.com>")
         .about("Rust cat")
@@ -42,50 +265,401 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Number nonblank lines"),
         )
-        .get_matches();
-
-    Ok(Config {
-        files: matches
-            .get_many::<String>("file")
-            .expect("files required")
-            .cloned()
-            .collect(),
-        number_lines: matches.get_flag("number"),
-        number_nonblank_lines: matches.get_flag("number-nonblank"),
-    })
+        .arg(
+            Arg::new("squeeze-blank")
+                .short('s')
+                .long("squeeze-blank")
+                .action(ArgAction::SetTrue)
+                .help("Suppress repeated blank lines"),
+        )
+        .arg(
+            Arg::new("number-per-file")
+                .long("number-per-file")
+                .action(ArgAction::SetTrue)
+                .help("Number lines from 1 in each file"),
+        )
+        .arg(
+            Arg::new("show-all")
+                .short('A')
+                .long("show-all")
+                .action(ArgAction::SetTrue)
+                .help("Equivalent to -vET"),
+        )
+        .arg(
+            Arg::new("e")
+                .short('e')
+                .action(ArgAction::SetTrue)
+                .help("Equivalent to -vE"),
+        )
+        .arg(
+            Arg::new("show-ends")
+                .short('E')
+                .long("show-ends")
+                .action(ArgAction::SetTrue)
+                .help("Show $ at the end of each line"),
+        )
+        .arg(
+            Arg::new("t")
+                .short('t')
+                .action(ArgAction::SetTrue)
+                .help("Equivalent to -vT"),
+        )
+        .arg(
+            Arg::new("show-tabs")
+                .short('T')
+                .long("show-tabs")
+                .action(ArgAction::SetTrue)
+                .help("Show tabs as ^I"),
+        )
+        .arg(
+            Arg::new("show-nonprinting")
+                .short('v')
+                .long("show-nonprinting")
+                .action(ArgAction::SetTrue)
+                .help("Use ^ and M- notation, except for line ends and tabs"),
+        )
+        .arg(
+            Arg::new("lines")
+                .long("lines")
+                .value_name("START:END")
+                .help("Print only lines START to END of each file")
+                .value_parser(LineRange::parse)
+                .conflicts_with_all(["from", "to"]),
+        )
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .value_name("REGEX")
+                .help("Print from each line matching REGEX")
+                .value_parser(Regex::new),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .value_name("REGEX")
+                .help("Print through the next line matching REGEX")
+                .value_parser(Regex::new),
+        )
+        .arg(
+            Arg::new("from-encoding")
+                .long("from-encoding")
+                .value_name("ENCODING")
+                .help("Decode input from ENCODING, unless a byte order mark names another")
+                .value_parser(encoding::parse),
+        )
+        .arg(
+            Arg::new("to-encoding")
+                .long("to-encoding")
+                .value_name("ENCODING")
+                .help("Encode output in ENCODING")
+                .value_parser(encoding::parse),
+        )
+        .arg(
+            Arg::new("strip-bom")
+                .long("strip-bom")
+                .action(ArgAction::SetTrue)
+                .help("Remove the byte order mark from the start of each file"),
+        )
+        .arg(
+            Arg::new("crlf-to-lf")
+                .long("crlf-to-lf")
+                .action(ArgAction::SetTrue)
+                .help("Convert CRLF line endings to LF"),
+        )
+        .arg(common::zero_terminated_arg())
+        .arg(Compat::arg())
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+    let flag = |id| matches.get_flag(id);
+    let all = flag("show-all");
+
+    Config::builder()
+        .files(
+            matches
+                .get_many::<String>("file")
+                .expect("files required")
+                .cloned(),
+        )
+        .number_lines(matches.get_flag("number"))
+        .number_nonblank_lines(matches.get_flag("number-nonblank"))
+        .zero_terminated(matches.get_flag("zero_terminated"))
+        .compat(Compat::from_matches(&matches))
+        .show_nonprinting(all || flag("e") || flag("t") || flag("show-nonprinting"))
+        .show_ends(all || flag("e") || flag("show-ends"))
+        .show_tabs(all || flag("t") || flag("show-tabs"))
+        .squeeze_blank(flag("squeeze-blank"))
+        .number_per_file(flag("number-per-file"))
+        .lines(matches.get_one("lines").copied())
+        .from(matches.get_one("from").cloned())
+        .to(matches.get_one("to").cloned())
+        .from_encoding(matches.get_one("from-encoding").copied())
+        .to_encoding(matches.get_one("to-encoding").copied())
+        .strip_bom(flag("strip-bom"))
+        .crlf_to_lf(flag("crlf-to-lf"))
+        .build()
+}
+
+pub fn run(config: Config) -> Result<()> {
+    #[cfg(unix)]
+    if config.is_plain() {
+        let mut out = File::from(io::stdout().as_fd().try_clone_to_owned()?);
+        return run_plain(&config, &mut out);
+    }
+    run_with(&config, common::open, common::stdout())
+}
+
+/// Copies `config`'s files onto `out` unchanged, leaving as much of the
+/// work as it can to the kernel. [`run`] does this when
+/// [`Config::is_plain`].
+///
+/// Files are opened with [`common::open_plain`], so compressed ones are
+/// still decompressed.
+#[cfg(unix)]
+pub fn run_plain(config: &Config, out: &mut File) -> Result<()> {
+    let mut failures = Failures::default();
+    let mut buf = vec![0; copy::BUF_SIZE];
+    for filename in &config.files {
+        if let Err(err) = copy_file(filename, out, &mut buf) {
+            failures.report(err)?;
+        }
+    }
+    failures.finish()
+}
+
+#[cfg(unix)]
+fn copy_file(filename: &str, out: &mut File, buf: &mut [u8]) -> Result<()> {
+    match common::open_plain(filename).map_err(|e| Error::open(filename, e))? {
+        Plain::File(mut file) => copy::copy(filename, &mut file, out, buf),
+        Plain::Decoded(mut reader) => copy::buffered(filename, &mut reader, out, buf),
+    }
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// Concatenates `config`'s files, opened through `inputs`, onto `out`.
+pub fn run_with(config: &Config, inputs: impl Inputs, out: impl Write) -> Result<()> {
+    match config.to_encoding {
+        Some(encoding) => {
+            let mut out = Encode::new(out, encoding);
+            let result = cat_files(config, inputs, &mut out);
+            let finished = out.finish();
+            result?;
+            Ok(finished?)
+        }
+        None => cat_files(config, inputs, out),
     }
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}", filename, err),
-            Ok(file) => {
-                let mut last_num = 0;
-                for (line_num, line) in file.lines().enumerate() {
-                    let line = line?;
-
-                    if config.number_lines {
-                        println!("{:>6}\t{}", line_num + 1, line)
-                    } else if config.number_nonblank_lines {
-                        if !line.is_empty() {
-                            last_num += 1;
-                            println!("{:>6}\t{}", last_num, line);
-                        } else {
-                            println!();
-                        }
-                    } else {
-                        println!("{}", line)
-                    }
+fn cat_files(config: &Config, mut inputs: impl Inputs, mut out: impl Write) -> Result<()> {
+    let mut failures = Failures::default();
+    let mut lines = Lines::default();
+    for filename in &config.files {
+        if config.number_per_file {
+            lines = Lines::default();
+        }
+        if let Err(err) = cat_file(filename, config, &mut inputs, &mut lines, &mut out) {
+            failures.report(err)?;
+        }
+    }
+    out.flush()?;
+    failures.finish()
+}
+
+/// Where the output stands, carried from one file to the next unless
+/// numbering is per file.
+#[derive(Debug, Default)]
+struct Lines {
+    /// The last line numbers written, for `-n` and `-b`.
+    all: i64,
+    nonblank: i64,
+    /// Whether the last line written was blank, for `-s`.
+    blank: bool,
+    /// Whether the last file ended part way through a line, which the next
+    /// file continues.
+    partial: bool,
+}
+
+fn cat_file(
+    filename: &str,
+    config: &Config,
+    inputs: &mut impl Inputs,
+    lines: &mut Lines,
+    out: &mut impl Write,
+) -> Result<()> {
+    let file = inputs
+        .open(filename)
+        .map_err(|e| Error::open(filename, e))?;
+    let file = encoding::decode(file, config.from_encoding, config.strip_bom)
+        .map_err(|e| Error::read(filename, e))?;
+    let mut records = Records::new(file, config.terminator);
+    let mut selector = config.selection.as_ref().map(Selector::new);
+    // Lines after the selection still count towards the next file's
+    // numbers, and whether it starts after a blank line.
+    let counting = (config.number_lines || config.number_nonblank_lines || config.squeeze_blank)
+        && !config.number_per_file;
+    let mut line = Vec::new();
+    let mut shown = Vec::new();
+    while records
+        .read(&mut line)
+        .map_err(|e| Error::read(filename, e))?
+    {
+        if config.crlf_to_lf && line.ends_with(b"\r\n") {
+            line.remove(line.len() - 2);
+        }
+        let selected = match selector.as_mut() {
+            Some(selector) if selector.done() && !counting => break,
+            Some(selector) => selector.select(records.strip(&line)),
+            None => true,
+        };
+        let blank = !lines.partial && line == [config.terminator];
+        if blank && lines.blank && config.squeeze_blank {
+            continue;
+        }
+
+        if !lines.partial {
+            lines.all += 1;
+            if config.number_lines && selected {
+                config.number_format.write(lines.all, out)?;
+            } else if config.number_nonblank_lines && !blank {
+                lines.nonblank += 1;
+                if selected {
+                    config.number_format.write(lines.nonblank, out)?;
                 }
             }
         }
+        lines.blank = blank;
+        lines.partial = line.last() != Some(&config.terminator);
+        if !selected {
+            continue;
+        }
+        if config.show.any() {
+            shown.clear();
+            config.show.write(&line, config.terminator, &mut shown);
+            out.write_all(&shown)?;
+        } else {
+            out.write_all(&line)?;
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config};
+    use common::Compat;
+    use std::io::{self, BufRead, Cursor};
+
+    fn inputs(name: &str) -> io::Result<Box<dyn BufRead>> {
+        match name {
+            "fox" => Ok(Box::new(Cursor::new("The quick brown fox\n\njumps\n"))),
+            "dog" => Ok(Box::new(Cursor::new("lazy dog\n"))),
+            _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+        }
+    }
+
+    #[test]
+    fn test_run_with() {
+        let config = Config::builder().files(["fox", "dog"]).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"The quick brown fox\n\njumps\nlazy dog\n");
+
+        let config = Config::builder()
+            .files(["fox"])
+            .number_nonblank_lines(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"     1\tThe quick brown fox\n\n     2\tjumps\n");
+    }
+
+    #[test]
+    fn test_run_with_compat() {
+        let builder = || Config::builder().files(["fox", "dog"]).number_lines(true);
+        let mut out = Vec::new();
+        assert!(run_with(&builder().build().unwrap(), inputs, &mut out).is_ok());
+        assert!(out.ends_with(b"     4\tlazy dog\n"));
+
+        let config = builder().compat(Some(Compat::Gnu)).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert!(out.ends_with(b"     4\tlazy dog\n"));
+
+        for config in [
+            builder().number_per_file(true),
+            builder().compat(Some(Compat::Bsd)),
+        ] {
+            let mut out = Vec::new();
+            assert!(run_with(&config.build().unwrap(), inputs, &mut out).is_ok());
+            assert!(out.ends_with(b"     1\tlazy dog\n"));
+        }
+    }
+
+    #[test]
+    fn test_run_with_squeeze_blank() {
+        let inputs = |name: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new(match name {
+                "a" => "a\n\n\n",
+                "b" => "\n\nb",
+                _ => "\nc\n",
+            })))
+        };
+        let builder = || {
+            Config::builder()
+                .files(["a", "b", "c"])
+                .squeeze_blank(true)
+                .number_nonblank_lines(true)
+        };
+        let mut out = Vec::new();
+        assert!(run_with(&builder().build().unwrap(), inputs, &mut out).is_ok());
+        assert_eq!(out, b"     1\ta\n\n     2\tb\n     3\tc\n");
+
+        let config = builder().number_per_file(true).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"     1\ta\n\n\n     1\tb\n     1\tc\n");
+    }
+
+    #[test]
+    fn test_run_with_missing() {
+        let config = Config::builder().files(["cat", "dog"]).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_err());
+        assert_eq!(out, b"lazy dog\n");
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = Config::builder().build().unwrap();
+        assert_eq!(config.files, ["-"]);
+
+        let res = Config::builder()
+            .number_lines(true)
+            .number_nonblank_lines(true)
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_run_with_zero_terminated() {
+        let inputs =
+            |_: &str| -> io::Result<Box<dyn BufRead>> { Ok(Box::new(Cursor::new("a\nb\0\0c"))) };
+        let config = Config::builder()
+            .number_nonblank_lines(true)
+            .zero_terminated(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"     1\ta\nb\0\0     2\tc");
+    }
+}
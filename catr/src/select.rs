@@ -0,0 +1,186 @@
+use regex::bytes::Regex;
+
+/// The lines `--lines START:END` picks out of each file, counting from 1.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LineRange {
+    pub start: u64,
+    /// The last line, or `None` to go on to the end of the file.
+    pub end: Option<u64>,
+}
+
+impl LineRange {
+    /// Parses `START:END`, where either side may be left out.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (start, end) = value
+            .split_once(':')
+            .ok_or_else(|| format!("invalid line range '{value}': expected START:END"))?;
+        let number = |n: &str| {
+            n.parse::<u64>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| format!("invalid line number '{n}' in '{value}'"))
+        };
+        let range = Self {
+            start: if start.is_empty() { 1 } else { number(start)? },
+            end: if end.is_empty() {
+                None
+            } else {
+                Some(number(end)?)
+            },
+        };
+        match range.end {
+            Some(end) if end < range.start => {
+                Err(format!("invalid line range '{value}': END is before START"))
+            }
+            _ => Ok(range),
+        }
+    }
+}
+
+/// How lines are picked out of each file.
+#[derive(Debug, Clone)]
+pub(crate) enum Selection {
+    Lines(LineRange),
+    /// Like sed's `/FROM/,/TO/`: from each line matching `from` through
+    /// the next one after it matching `to`. Without `from` there is just
+    /// one range, from the first line; without `to`, ranges run to the end
+    /// of the file.
+    Pattern {
+        from: Option<Regex>,
+        to: Option<Regex>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum State {
+    Before,
+    Inside,
+    After,
+}
+
+/// Picks lines out of one file.
+#[derive(Debug)]
+pub(crate) struct Selector<'a> {
+    selection: &'a Selection,
+    line: u64,
+    state: State,
+}
+
+impl<'a> Selector<'a> {
+    pub fn new(selection: &'a Selection) -> Self {
+        let state = match selection {
+            Selection::Pattern { from: None, .. } => State::Inside,
+            _ => State::Before,
+        };
+        Self {
+            selection,
+            line: 0,
+            state,
+        }
+    }
+
+    /// Whether to write the next line, given without its terminator.
+    pub fn select(&mut self, line: &[u8]) -> bool {
+        self.line += 1;
+        match self.selection {
+            Selection::Lines(range) => {
+                let selected =
+                    self.line >= range.start && range.end.is_none_or(|end| self.line <= end);
+                if range.end.is_some_and(|end| self.line >= end) {
+                    self.state = State::After;
+                }
+                selected
+            }
+            Selection::Pattern { from, to } => match self.state {
+                State::Before => {
+                    let starts = from.as_ref().is_some_and(|from| from.is_match(line));
+                    if starts {
+                        self.state = State::Inside;
+                    }
+                    starts
+                }
+                State::Inside => {
+                    if to.as_ref().is_some_and(|to| to.is_match(line)) {
+                        self.state = if from.is_some() {
+                            State::Before
+                        } else {
+                            State::After
+                        };
+                    }
+                    true
+                }
+                State::After => false,
+            },
+        }
+    }
+
+    /// Whether no more lines of the file will be selected.
+    pub fn done(&self) -> bool {
+        self.state == State::After
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineRange, Selection, Selector};
+    use regex::bytes::Regex;
+
+    fn run(selection: &Selection, lines: &[&str]) -> (Vec<usize>, bool) {
+        let mut selector = Selector::new(selection);
+        let picked = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| selector.select(line.as_bytes()))
+            .map(|(i, _)| i + 1)
+            .collect();
+        (picked, selector.done())
+    }
+
+    #[test]
+    fn test_parse() {
+        let range = |start, end| Ok(LineRange { start, end });
+        assert_eq!(LineRange::parse("3:5"), range(3, Some(5)));
+        assert_eq!(LineRange::parse("3:3"), range(3, Some(3)));
+        assert_eq!(LineRange::parse("3:"), range(3, None));
+        assert_eq!(LineRange::parse(":5"), range(1, Some(5)));
+        assert_eq!(LineRange::parse(":"), range(1, None));
+        assert!(LineRange::parse("3").is_err());
+        assert!(LineRange::parse("0:5").is_err());
+        assert!(LineRange::parse("5:3").is_err());
+        assert!(LineRange::parse("a:b").is_err());
+    }
+
+    #[test]
+    fn test_lines() {
+        let lines = ["a", "b", "c", "d", "e"];
+        let select = |start, end| Selection::Lines(LineRange { start, end });
+        assert_eq!(run(&select(2, Some(3)), &lines), (vec![2, 3], true));
+        assert_eq!(run(&select(4, None), &lines), (vec![4, 5], false));
+        assert_eq!(run(&select(5, Some(5)), &lines), (vec![5], true));
+        assert_eq!(run(&select(6, Some(9)), &lines), (vec![], false));
+    }
+
+    #[test]
+    fn test_pattern() {
+        let lines = ["x", "ERROR 1", "at a", "", "x", "ERROR 2", "", "x"];
+        let regex = |re| Some(Regex::new(re).unwrap());
+        let select = |from, to| Selection::Pattern { from, to };
+        assert_eq!(
+            run(&select(regex("ERROR"), regex("^$")), &lines),
+            (vec![2, 3, 4, 6, 7], false)
+        );
+        assert_eq!(
+            run(&select(regex("ERROR"), None), &lines),
+            (vec![2, 3, 4, 5, 6, 7, 8], false)
+        );
+        assert_eq!(
+            run(&select(None, regex("^$")), &lines),
+            (vec![1, 2, 3, 4], true)
+        );
+        // The end is looked for from the line after the start.
+        assert_eq!(
+            run(&select(regex("x"), regex("x")), &lines),
+            (vec![1, 2, 3, 4, 5, 8], false)
+        );
+    }
+}
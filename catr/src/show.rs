@@ -0,0 +1,112 @@
+/// Which bytes `-v`, `-E` and `-T` make visible.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Show {
+    pub nonprinting: bool,
+    pub ends: bool,
+    pub tabs: bool,
+}
+
+impl Show {
+    pub fn any(&self) -> bool {
+        self.nonprinting || self.ends || self.tabs
+    }
+
+    /// Appends `line` to `out` as GNU cat shows it:
+    ///
+    /// - with `ends`, `$` before each newline, and `^M` for a carriage
+    ///   return before one
+    /// - with `tabs`, `^I` for each tab
+    /// - with `nonprinting`, `^` notation for other control bytes and DEL,
+    ///   and `M-` before bytes above 127
+    ///
+    /// A trailing `terminator` other than newline is written as it is.
+    pub fn write(&self, line: &[u8], terminator: u8, out: &mut Vec<u8>) {
+        let (text, end) = match line.split_last() {
+            Some((&last, text)) if last == terminator && terminator != b'\n' => (text, Some(last)),
+            _ => (line, None),
+        };
+        for (i, &b) in text.iter().enumerate() {
+            match b {
+                b'\n' if self.ends => out.extend_from_slice(b"$\n"),
+                // Shows a CRLF line end as ^M$.
+                b'\r' if self.ends && text.get(i + 1) == Some(&b'\n') => {
+                    out.extend_from_slice(b"^M")
+                }
+                b'\n' => out.push(b),
+                b'\t' if self.tabs => out.extend_from_slice(b"^I"),
+                b'\t' => out.push(b),
+                b if self.nonprinting => caret(b, out),
+                b => out.push(b),
+            }
+        }
+        out.extend(end);
+    }
+}
+
+/// The `-v` form of a byte other than newline and tab.
+fn caret(b: u8, out: &mut Vec<u8>) {
+    let b = if b >= 128 {
+        out.extend_from_slice(b"M-");
+        b - 128
+    } else {
+        b
+    };
+    match b {
+        0..=31 => out.extend_from_slice(&[b'^', b + 64]),
+        127 => out.extend_from_slice(b"^?"),
+        b => out.push(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Show;
+
+    fn run(show: Show, line: &[u8], terminator: u8) -> Vec<u8> {
+        let mut out = Vec::new();
+        show.write(line, terminator, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_write() {
+        let line = b"a\tb\r\x1b\x7f\x00\xe9\xff\x80\xa0\n";
+        let all = Show {
+            nonprinting: true,
+            ends: true,
+            tabs: true,
+        };
+        assert_eq!(run(all, line, b'\n'), b"a^Ib^M^[^?^@M-iM-^?M-^@M- $\n");
+        let nonprinting = Show {
+            nonprinting: true,
+            ..Show::default()
+        };
+        assert_eq!(
+            run(nonprinting, line, b'\n'),
+            b"a\tb^M^[^?^@M-iM-^?M-^@M- \n"
+        );
+        let ends = Show {
+            ends: true,
+            ..Show::default()
+        };
+        assert_eq!(run(ends, b"a\r\n", b'\n'), b"a^M$\n");
+        assert_eq!(run(ends, b"a\rb\r", b'\n'), b"a\rb\r");
+        assert_eq!(run(ends, b"no newline", b'\n'), b"no newline");
+        let tabs = Show {
+            tabs: true,
+            ..Show::default()
+        };
+        assert_eq!(run(tabs, b"\ta\t\n", b'\n'), b"^Ia^I\n");
+    }
+
+    #[test]
+    fn test_write_zero_terminated() {
+        let all = Show {
+            nonprinting: true,
+            ends: true,
+            tabs: true,
+        };
+        assert_eq!(run(all, b"a\nb\x01\0", b'\0'), b"a$\nb^A\0");
+        assert_eq!(run(all, b"a\0b", b'\0'), b"a^@b");
+    }
+}
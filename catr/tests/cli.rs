@@ -1,13 +1,24 @@
-use assert_cmd::Command;
+use assert_cmd::{cargo::cargo_bin, Command};
 use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
-use std::fs;
+use std::{fs, process};
 
 const PRG: &str = "catr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
+const FOX_GZ: &str = "tests/inputs/fox.txt.gz";
+const FOX_BZ2: &str = "tests/inputs/fox.txt.bz2";
+const FOX_XZ: &str = "tests/inputs/fox.txt.xz";
+const FOX_ZST: &str = "tests/inputs/fox.txt.zst";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const CTRL: &str = "tests/inputs/ctrl.txt";
+const BLANKS: &str = "tests/inputs/blanks.txt";
+const ERRORS: &str = "tests/inputs/errors.txt";
+const UTF16: &str = "tests/inputs/utf16le.txt";
+const CP1252: &str = "tests/inputs/cp1252.txt";
+const ALL: [&str; 4] = [EMPTY, FOX, SPIDERS, BUSTLE];
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
 
@@ -26,7 +37,7 @@ fn gen_bad_file() -> String {
 }
 
 fn run(args: &[&str], expected_file: &str) -> TestResult {
-    let expected = fs::read_to_string(expected_file)?;
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
@@ -36,8 +47,8 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
 }
 
 fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> TestResult {
-    let input = fs::read_to_string(input_file)?;
-    let expected = fs::read_to_string(expected_file)?;
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .write_stdin(input)
@@ -54,11 +65,107 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+#[test]
+fn continues_after_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/fox.txt.out")?;
+    Command::cargo_bin(PRG)?
+        .args([&bad, FOX])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn dies_lines_and_pattern() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--lines", "2:4", "--from", "ERROR", ERRORS])
+        .assert()
+        .failure()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn dies_bad_line_range() -> TestResult {
+    for range in ["4", "0:3", "5:2", "a:"] {
+        Command::cargo_bin(PRG)?
+            .args(["--lines", range, FOX])
+            .assert()
+            .failure()
+            .code(2)
+            .stderr(predicate::str::contains(range));
+    }
+    Ok(())
+}
+
+#[test]
+fn dies_bad_encoding() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "utf-9", FOX])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("utf-9"));
+    Ok(())
+}
+
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, [BUSTLE; 1000])
+}
+
+#[test]
+#[cfg(unix)]
+fn appends_to_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("out");
+    fs::write(&path, "start\n")?;
+    let out = fs::OpenOptions::new().append(true).open(&path)?;
+    let status = process::Command::new(cargo_bin(PRG))
+        .args([FOX, "-", FOX_GZ])
+        .stdin(fs::File::open(SPIDERS)?)
+        .stdout(out)
+        .status()?;
+    assert!(status.success());
+    let fox = fs::read_to_string("tests/expected/fox.txt.out")?;
+    let spiders = fs::read_to_string(SPIDERS)?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        format!("start\n{fox}{spiders}{fox}")
+    );
+    Ok(())
+}
+
+#[test]
+fn zero_terminated_number() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n"])
+        .write_stdin("a\nb\0c\0")
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"     1\ta\nb\0     2\tc\0"[..]));
+    Ok(())
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--number-nonblank"));
+    Ok(())
+}
+
 #[test]
 fn bustle() -> TestResult {
     run(&[BUSTLE], "tests/expected/the-bustle.txt.out")
@@ -127,6 +234,59 @@ fn fox_b() -> TestResult {
     run(&["-b", FOX], "tests/expected/fox.txt.b.out")
 }
 
+#[test]
+fn fox_gz() -> TestResult {
+    run(&[FOX_GZ], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn fox_bz2() -> TestResult {
+    run(&[FOX_BZ2], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn fox_xz() -> TestResult {
+    run(&[FOX_XZ], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn fox_zst() -> TestResult {
+    run(&[FOX_ZST], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn fox_gz_stdin_n() -> TestResult {
+    let input = fs::read(FOX_GZ)?;
+    let expected = fs::read_to_string("tests/expected/fox.txt.n.out")?;
+    Command::cargo_bin(PRG)?
+        .arg("-n")
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn latin1() -> TestResult {
+    run(&[LATIN1], "tests/expected/latin1.txt.out")
+}
+
+#[test]
+fn latin1_n() -> TestResult {
+    run(&["-n", LATIN1], "tests/expected/latin1.txt.n.out")
+}
+
+#[test]
+fn latin1_b() -> TestResult {
+    run(&["-b", LATIN1], "tests/expected/latin1.txt.b.out")
+}
+
+#[test]
+fn latin1_stdin() -> TestResult {
+    run_stdin(LATIN1, &["-"], "tests/expected/latin1.txt.out")
+}
+
 #[test]
 fn empty() -> TestResult {
     run(&[EMPTY], "tests/expected/empty.txt.out")
@@ -141,3 +301,191 @@ fn empty_n() -> TestResult {
 fn empty_b() -> TestResult {
     run(&["-b", EMPTY], "tests/expected/empty.txt.b.out")
 }
+
+#[test]
+fn ctrl_v() -> TestResult {
+    run(&["-v", CTRL], "tests/expected/ctrl.txt.v.out")
+}
+
+#[test]
+fn ctrl_show_ends() -> TestResult {
+    run(&["--show-ends", CTRL], "tests/expected/ctrl.txt.E.out")
+}
+
+#[test]
+fn ctrl_show_tabs() -> TestResult {
+    run(&["-T", CTRL], "tests/expected/ctrl.txt.T.out")
+}
+
+#[test]
+fn ctrl_show_all() -> TestResult {
+    run(&["-A", CTRL], "tests/expected/ctrl.txt.A.out")
+}
+
+#[test]
+fn ctrl_show_all_n() -> TestResult {
+    run(&["-A", "-n", CTRL], "tests/expected/ctrl.txt.An.out")
+}
+
+#[test]
+fn ctrl_vet() -> TestResult {
+    run(&["-vET", CTRL], "tests/expected/ctrl.txt.A.out")
+}
+
+#[test]
+fn ctrl_e_t() -> TestResult {
+    run(&["-e", "-t", CTRL], "tests/expected/ctrl.txt.A.out")
+}
+
+#[test]
+fn latin1_v() -> TestResult {
+    run(&["-v", LATIN1], "tests/expected/latin1.txt.v.out")
+}
+
+#[test]
+fn latin1_show_all() -> TestResult {
+    run(&["-A", LATIN1], "tests/expected/latin1.txt.A.out")
+}
+
+#[test]
+fn all() -> TestResult {
+    run(&ALL, "tests/expected/all.out")
+}
+
+#[test]
+fn all_n() -> TestResult {
+    run(&[&["-n"], &ALL[..]].concat(), "tests/expected/all.n.out")
+}
+
+#[test]
+fn all_b() -> TestResult {
+    run(&[&["-b"], &ALL[..]].concat(), "tests/expected/all.b.out")
+}
+
+#[test]
+fn all_n_per_file() -> TestResult {
+    run(
+        &[&["-n", "--number-per-file"], &ALL[..]].concat(),
+        "tests/expected/all.n.per-file.out",
+    )
+}
+
+#[test]
+fn blanks_s() -> TestResult {
+    run(&["-s", BLANKS, BLANKS], "tests/expected/blanks.txt.s.out")
+}
+
+#[test]
+fn blanks_sn() -> TestResult {
+    run(
+        &["--squeeze-blank", "-n", BLANKS, BLANKS],
+        "tests/expected/blanks.txt.sn.out",
+    )
+}
+
+#[test]
+fn blanks_sb() -> TestResult {
+    run(&["-sb", BLANKS, BLANKS], "tests/expected/blanks.txt.sb.out")
+}
+
+#[test]
+fn all_lines() -> TestResult {
+    run(
+        &[&["--lines", "2:4"], &ALL[..]].concat(),
+        "tests/expected/all.lines.out",
+    )
+}
+
+#[test]
+fn all_lines_n() -> TestResult {
+    run(
+        &[&["-n", "--lines", "2:4"], &ALL[..]].concat(),
+        "tests/expected/all.lines.n.out",
+    )
+}
+
+#[test]
+fn all_lines_b() -> TestResult {
+    run(
+        &[&["-b", "--lines", "4:"], &ALL[..]].concat(),
+        "tests/expected/all.lines.b.out",
+    )
+}
+
+#[test]
+fn errors_from_to() -> TestResult {
+    run(
+        &["--from", "ERROR", "--to", "^$", ERRORS],
+        "tests/expected/errors.txt.from-to.out",
+    )
+}
+
+#[test]
+fn errors_from_to_n() -> TestResult {
+    run(
+        &["-n", "--from", "ERROR", "--to", "^$", ERRORS, ERRORS],
+        "tests/expected/errors.txt.from-to.n.out",
+    )
+}
+
+#[test]
+fn utf16_detected() -> TestResult {
+    run(
+        &["--to-encoding", "utf-8", UTF16],
+        "tests/expected/utf16le.txt.utf8.out",
+    )
+}
+
+#[test]
+fn utf16_strip_n() -> TestResult {
+    run(
+        &[
+            "--from-encoding",
+            "windows-1252",
+            "--strip-bom",
+            "--crlf-to-lf",
+            "-n",
+            UTF16,
+        ],
+        "tests/expected/utf16le.txt.strip.n.out",
+    )
+}
+
+#[test]
+fn utf16_to_cp1252() -> TestResult {
+    run(
+        &["--to-encoding", "cp1252", "--strip-bom", UTF16],
+        "tests/expected/utf16le.txt.cp1252.out",
+    )
+}
+
+#[test]
+fn cp1252_to_utf8() -> TestResult {
+    run(
+        &["--from-encoding", "windows-1252", CP1252],
+        "tests/expected/cp1252.txt.utf8.out",
+    )
+}
+
+#[test]
+fn cp1252_to_utf16() -> TestResult {
+    run(
+        &[
+            "--from-encoding",
+            "cp1252",
+            "--to-encoding",
+            "utf-16le",
+            CP1252,
+        ],
+        "tests/expected/cp1252.txt.utf16le.out",
+    )
+}
+
+#[test]
+fn cp1252_stdin() -> TestResult {
+    run_stdin(
+        CP1252,
+        &["--from-encoding", "cp1252"],
+        "tests/expected/cp1252.txt.utf8.out",
+    )
+}
@@ -0,0 +1,5 @@
+/// Runs every case in the corpus under each `--compat` profile.
+#[test]
+fn corpus() -> Result<(), Box<dyn std::error::Error>> {
+    common::testing::compat_corpus("catr", "tests/compat.toml")
+}
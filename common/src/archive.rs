@@ -0,0 +1,450 @@
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    rc::Rc,
+};
+
+use flate2::read::DeflateDecoder;
+use zip::{CompressionMethod, ZipArchive};
+
+use crate::input::decompress;
+
+/// Joins the path of an archive to the path of a member inside it, as in
+/// `bundle.tar.gz//data/users.tsv`.
+pub const SEPARATOR: &str = "//";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MemberType {
+    Dir,
+    File,
+    Link,
+    Other,
+}
+
+/// An entry of a tar or zip archive, as listed by [`members`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Member {
+    /// Path inside the archive, without a leading "./" or trailing "/".
+    pub path: String,
+    pub member_type: MemberType,
+    pub size: u64,
+    /// Seconds since the Unix epoch. Zip times carry no zone and are taken
+    /// as UTC.
+    pub mtime: i64,
+}
+
+enum Archive<F> {
+    /// A tar stream, already decompressed.
+    Tar(Box<dyn BufRead>),
+    Zip(F),
+}
+
+impl<F: Read + Seek + 'static> Archive<F> {
+    /// Reads `file` as a zip file or a possibly compressed tar file, if it
+    /// is one.
+    fn open(mut file: F) -> io::Result<Option<Self>> {
+        let mut magic = Vec::with_capacity(4);
+        (&mut file).take(4).read_to_end(&mut magic)?;
+        file.rewind()?;
+        if magic == b"PK\x03\x04" || magic == b"PK\x05\x06" {
+            return Ok(Some(Self::Zip(file)));
+        }
+
+        // The ustar magic sits at offset 257 of the first header, further
+        // in than a single read of a decompressor is sure to reach.
+        let mut reader = decompress(file)?;
+        let mut header = Vec::with_capacity(512);
+        (&mut reader).take(512).read_to_end(&mut header)?;
+        Ok(match header.get(257..262) {
+            Some(b"ustar") => Some(Self::Tar(Box::new(Cursor::new(header).chain(reader)))),
+            _ => None,
+        })
+    }
+}
+
+/// Splits `name` into an archive and a member path when it does not name a
+/// file itself but a prefix ending before a "//" does.
+pub fn split(name: &str) -> Option<(&str, &str)> {
+    if Path::new(name).exists() {
+        return None;
+    }
+    name.match_indices(SEPARATOR)
+        .map(|(i, _)| (&name[..i], &name[i + SEPARATOR.len()..]))
+        .find(|(archive, _)| !archive.is_empty() && Path::new(archive).is_file())
+}
+
+/// Opens `member` of `archive` as a stream.
+///
+/// Tar members are read straight out of the (decompressed) archive stream;
+/// zip members must be stored or deflated.
+pub fn open_member(archive: &str, member: &str) -> io::Result<Box<dyn BufRead>> {
+    match Archive::open(File::open(archive)?)? {
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a tar or zip archive",
+        )),
+        Some(Archive::Tar(reader)) => open_tar_member(reader, member),
+        Some(Archive::Zip(file)) => open_zip_member(file, member),
+    }
+}
+
+/// Lists the members of `path`, or returns None if it is not an archive or
+/// does not decode as one. Only errors reading the file itself are
+/// returned, so any file can be probed.
+pub fn members(path: &str) -> io::Result<Option<Vec<Member>>> {
+    let failed = Rc::new(Cell::new(false));
+    let file = Watched {
+        file: File::open(path)?,
+        failed: Rc::clone(&failed),
+    };
+    let listed = Archive::open(file).and_then(|archive| match archive {
+        None => Ok(None),
+        Some(Archive::Tar(reader)) => tar_members(reader).map(Some),
+        Some(Archive::Zip(file)) => zip_members(file).map(Some),
+    });
+    match listed {
+        Err(_) if !failed.get() => Ok(None),
+        listed => listed,
+    }
+}
+
+/// A file that notes when reading it fails, telling errors of the file
+/// itself apart from its contents not decoding.
+struct Watched {
+    file: File,
+    failed: Rc<Cell<bool>>,
+}
+
+impl Watched {
+    fn watch<T>(&self, result: io::Result<T>) -> io::Result<T> {
+        if result
+            .as_ref()
+            .is_err_and(|e| e.kind() != io::ErrorKind::Interrupted)
+        {
+            self.failed.set(true);
+        }
+        result
+    }
+}
+
+impl Read for Watched {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result = self.file.read(buf);
+        self.watch(result)
+    }
+}
+
+impl Seek for Watched {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let result = self.file.seek(pos);
+        self.watch(result)
+    }
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_start_matches("./").trim_end_matches('/')
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "no such member in archive")
+}
+
+fn not_a_file(member_type: MemberType) -> io::Error {
+    match member_type {
+        MemberType::Dir => io::Error::from(io::ErrorKind::IsADirectory),
+        _ => io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "archive member is not a regular file",
+        ),
+    }
+}
+
+fn tar_member_type(entry_type: tar::EntryType) -> MemberType {
+    if entry_type.is_dir() {
+        MemberType::Dir
+    } else if entry_type.is_file() || entry_type.is_contiguous() {
+        MemberType::File
+    } else if entry_type.is_symlink() || entry_type.is_hard_link() {
+        MemberType::Link
+    } else {
+        MemberType::Other
+    }
+}
+
+fn open_tar_member(reader: Box<dyn BufRead>, member: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut size = None;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if normalize(&String::from_utf8_lossy(&entry.path_bytes())) == normalize(member) {
+            match tar_member_type(entry.header().entry_type()) {
+                MemberType::File => size = Some(entry.size()),
+                other => return Err(not_a_file(other)),
+            }
+            break;
+        }
+    }
+    // Once an entry has been returned, the archive stream is positioned at
+    // the start of its data.
+    let size = size.ok_or_else(not_found)?;
+    Ok(Box::new(archive.into_inner().take(size)))
+}
+
+fn tar_members(reader: Box<dyn BufRead>) -> io::Result<Vec<Member>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut members = vec![];
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let header = entry.header();
+        members.push(Member {
+            path: normalize(&path).to_string(),
+            member_type: tar_member_type(header.entry_type()),
+            size: entry.size(),
+            mtime: header.mtime().map_or(0, |t| t as i64),
+        });
+    }
+    Ok(members)
+}
+
+fn zip_member_type(file: &zip::read::ZipFile) -> MemberType {
+    const S_IFMT: u32 = 0o170000;
+    const S_IFLNK: u32 = 0o120000;
+    if file.is_dir() {
+        MemberType::Dir
+    } else if file
+        .unix_mode()
+        .is_some_and(|mode| mode & S_IFMT == S_IFLNK)
+    {
+        MemberType::Link
+    } else {
+        MemberType::File
+    }
+}
+
+fn open_zip_member(file: File, member: &str) -> io::Result<Box<dyn BufRead>> {
+    let mut archive = ZipArchive::new(file)?;
+    let mut found = None;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        if normalize(file.name()) == normalize(member) {
+            match zip_member_type(&file) {
+                MemberType::File => {
+                    found = Some((
+                        file.data_start(),
+                        file.compressed_size(),
+                        file.compression(),
+                    ))
+                }
+                other => return Err(not_a_file(other)),
+            }
+            break;
+        }
+    }
+    let (start, len, method) = found.ok_or_else(not_found)?;
+
+    let mut file = archive.into_inner();
+    file.seek(SeekFrom::Start(start))?;
+    let data = file.take(len);
+    match method {
+        CompressionMethod::Stored => Ok(Box::new(BufReader::new(data))),
+        CompressionMethod::Deflated => Ok(Box::new(BufReader::new(DeflateDecoder::new(data)))),
+        other => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported zip compression method {other}"),
+        )),
+    }
+}
+
+fn zip_members(file: impl Read + Seek) -> io::Result<Vec<Member>> {
+    let mut archive = ZipArchive::new(file)?;
+    let mut members = vec![];
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        let time = file.last_modified();
+        let days = days_from_civil(time.year().into(), time.month().into(), time.day().into());
+        members.push(Member {
+            path: normalize(file.name()).to_string(),
+            member_type: zip_member_type(&file),
+            size: file.size(),
+            mtime: days * 86400
+                + i64::from(time.hour()) * 3600
+                + i64::from(time.minute()) * 60
+                + i64::from(time.second()),
+        });
+    }
+    Ok(members)
+}
+
+/// Days from 1970-01-01 to the given proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{days_from_civil, members, open_member, split, Member, MemberType};
+    use flate2::{write::GzEncoder, Compression};
+    use std::{
+        fs::File,
+        io::{self, Read, Write},
+        path::Path,
+    };
+    use tempfile::TempDir;
+    use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    const TEXT: &str = "The quick brown fox\njumps over the lazy dog.\n";
+
+    fn write_tar_gz(path: &Path) {
+        let gz = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        let mut tar = tar::Builder::new(gz);
+        let mut header = tar::Header::new_ustar();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mtime(1698566400);
+        tar.append_data(&mut header, "./data/", io::empty())
+            .unwrap();
+        let mut header = tar::Header::new_ustar();
+        header.set_size(TEXT.len() as u64);
+        header.set_mtime(1698566400);
+        tar.append_data(&mut header, "./data/fox.txt", TEXT.as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_zip(path: &Path) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, method) in [
+            ("stored.txt", CompressionMethod::Stored),
+            ("deflated.txt", CompressionMethod::Deflated),
+        ] {
+            let options = FileOptions::default().compression_method(method);
+            zip.start_file(name, options).unwrap();
+            zip.write_all(TEXT.as_bytes()).unwrap();
+        }
+        zip.add_directory("empty/", FileOptions::default()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn read(archive: &Path, member: &str) -> io::Result<String> {
+        let mut text = String::new();
+        open_member(archive.to_str().unwrap(), member)?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    #[test]
+    fn test_tar() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        write_tar_gz(&path);
+
+        assert_eq!(read(&path, "data/fox.txt").unwrap(), TEXT);
+        assert_eq!(read(&path, "./data/fox.txt").unwrap(), TEXT);
+        let err = read(&path, "data/dog.txt").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = read(&path, "data").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::IsADirectory);
+
+        let members = members(path.to_str().unwrap()).unwrap().unwrap();
+        assert_eq!(
+            members,
+            [
+                Member {
+                    path: "data".to_string(),
+                    member_type: MemberType::Dir,
+                    size: 0,
+                    mtime: 1698566400,
+                },
+                Member {
+                    path: "data/fox.txt".to_string(),
+                    member_type: MemberType::File,
+                    size: TEXT.len() as u64,
+                    mtime: 1698566400,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("export.zip");
+        write_zip(&path);
+
+        assert_eq!(read(&path, "stored.txt").unwrap(), TEXT);
+        assert_eq!(read(&path, "deflated.txt").unwrap(), TEXT);
+        let err = read(&path, "empty").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::IsADirectory);
+
+        let members = members(path.to_str().unwrap()).unwrap().unwrap();
+        let types: Vec<_> = members
+            .iter()
+            .map(|m| (m.path.as_str(), m.member_type))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("stored.txt", MemberType::File),
+                ("deflated.txt", MemberType::File),
+                ("empty", MemberType::Dir),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_archive() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("plain.txt");
+        std::fs::write(&path, TEXT).unwrap();
+        assert!(members(path.to_str().unwrap()).unwrap().is_none());
+        let err = read(&path, "x").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_undecodable() {
+        let dir = TempDir::new().unwrap();
+        let tar_gz = dir.path().join("bundle.tar.gz");
+        write_tar_gz(&tar_gz);
+        let zip = dir.path().join("export.zip");
+        write_zip(&zip);
+        for path in [tar_gz, zip] {
+            let mut bytes = std::fs::read(&path).unwrap();
+            bytes.truncate(bytes.len() / 2);
+            std::fs::write(&path, bytes).unwrap();
+            assert!(members(path.to_str().unwrap()).unwrap().is_none());
+        }
+
+        let missing = dir.path().join("missing.zip");
+        assert!(members(missing.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+        write_tar_gz(&path);
+        let archive = path.to_str().unwrap();
+
+        let name = format!("{archive}//data/fox.txt");
+        assert_eq!(split(&name), Some((archive, "data/fox.txt")));
+        assert_eq!(split(archive), None);
+        let name = format!("{}//bundle.tar.gz", dir.path().display());
+        assert_eq!(split(&name), None);
+        assert_eq!(split("no/such//file"), None);
+    }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(days_from_civil(2023, 10, 29), 19659);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::HashMap, ffi::OsString, io::Write, iter::once, process};
+
+use clap::{ArgMatches, Command};
+
+use crate::{defaults, generate, Error, Generate, Result};
+
+/// Parses `args` with `cmd`, plus a hidden `--generate` option and, for
+/// commands without subcommands, the defaults options `--no-config` and
+/// `--print-config`.
+///
+/// Unless `--no-config` is given, options from the tool's config file (see
+/// [`config_path`](crate::config_path)) and then its `<TOOL>_OPTS`
+/// environment variable are added beneath those in `args`.
+///
+/// Like `--help`, `--generate` and `--print-config` are handled here: the
+/// output is written to stdout and the process exits.
+pub fn get_matches_from<I, T>(cmd: Command, args: I) -> Result<ArgMatches>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let with_defaults = !cmd.has_subcommands() && !cmd.is_allow_external_subcommands_set();
+    let mut cmd = cmd.arg(generate::arg());
+    if with_defaults {
+        cmd = cmd.args(defaults::args());
+    }
+
+    let mut args = args.into_iter().map(Into::into);
+    let arg0 = args.next().unwrap_or_else(|| cmd.get_name().into());
+    let mut args: Vec<OsString> = args.collect();
+    let given = |flag: &str| {
+        args.iter().take_while(|arg| *arg != "--").any(|arg| {
+            let arg = arg.to_string_lossy();
+            arg == flag || arg.starts_with(&format!("{flag}="))
+        })
+    };
+    let (no_config, print_config) = (given("--no-config"), given("--print-config"));
+    // clap lets the exclusive --generate skip required arguments, but not
+    // required groups.
+    let generating = given("--generate");
+
+    let mut sources = HashMap::new();
+    if with_defaults && !no_config {
+        let layers = defaults::layers(cmd.get_name())?;
+        (args, sources) = defaults::merge(&cmd, layers, args)?;
+    }
+
+    let parser = if print_config || generating {
+        defaults::relax(&cmd)
+    } else {
+        cmd.clone()
+    };
+    let matches = parser.get_matches_from(once(arg0).chain(args));
+
+    let mut out = crate::stdout();
+    let result = if let Some(&target) = matches.get_one::<Generate>("generate") {
+        generate(target, &mut cmd, &mut out)
+    } else if print_config {
+        defaults::print_config(&cmd, &matches, &sources, no_config, &mut out)
+    } else {
+        return Ok(matches);
+    };
+    if let Err(e) = result.and_then(|_| out.flush()) {
+        Error::Write(e).exit();
+    }
+    process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::get_matches_from;
+    use clap::{Arg, Command};
+
+    fn command() -> Command {
+        Command::new("toolr").arg(Arg::new("files").long("file").required(true))
+    }
+
+    #[test]
+    fn test_get_matches_from() {
+        let matches = get_matches_from(command(), ["toolr", "--file", "a"]).unwrap();
+        assert_eq!(matches.get_one::<String>("files").unwrap(), "a");
+        assert!(!matches.get_flag("no_config"));
+    }
+}
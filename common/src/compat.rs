@@ -0,0 +1,44 @@
+use clap::{builder::PossibleValue, Arg, ArgMatches, ValueEnum};
+
+/// Another implementation whose behavior a tool follows with `--compat`.
+/// Without it, tools keep their own behavior. The differences are listed in
+/// `docs/compat.md`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Compat {
+    /// GNU coreutils.
+    Gnu,
+    /// FreeBSD and macOS.
+    Bsd,
+    /// The POSIX specification.
+    Posix,
+}
+
+impl ValueEnum for Compat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Gnu, Self::Bsd, Self::Posix]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Gnu => PossibleValue::new("gnu").help("GNU coreutils"),
+            Self::Bsd => PossibleValue::new("bsd").help("FreeBSD and macOS"),
+            Self::Posix => PossibleValue::new("posix").help("The POSIX specification"),
+        })
+    }
+}
+
+impl Compat {
+    /// The `--compat <PROFILE>` option.
+    pub fn arg() -> Arg {
+        Arg::new("compat")
+            .long("compat")
+            .value_name("PROFILE")
+            .value_parser(clap::value_parser!(Compat))
+            .help("Behave like another implementation")
+    }
+
+    /// Reads the option added by [`Compat::arg`].
+    pub fn from_matches(matches: &ArgMatches) -> Option<Self> {
+        matches.get_one("compat").copied()
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fmt, fs, io,
+    iter::once,
+    path::{Path, PathBuf},
+};
+
+use clap::{parser::ValueSource, Arg, ArgAction, ArgMatches, Command};
+use toml::Value;
+
+use crate::{Error, Result};
+
+/// Where an option given on top of the built-in defaults came from, from
+/// lowest to highest precedence.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Source {
+    ConfigFile,
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ConfigFile => "config file",
+            Self::Environment => "environment",
+            Self::CommandLine => "command line",
+        })
+    }
+}
+
+/// Options that control the defaults rather than the tool, so they are
+/// never taken from a config file or the environment.
+const OWN_IDS: &[&str] = &["no_config", "print_config", "generate", "help", "version"];
+
+/// The `--no-config` and `--print-config` flags.
+pub(crate) fn args() -> [Arg; 2] {
+    [
+        Arg::new("no_config")
+            .long("no-config")
+            .action(ArgAction::SetTrue)
+            .help("Ignore the config file and environment defaults"),
+        Arg::new("print_config")
+            .long("print-config")
+            .action(ArgAction::SetTrue)
+            .help("Print the effective configuration and exit"),
+    ]
+}
+
+/// Returns `$XDG_CONFIG_HOME/rutils/<tool>.toml`, where `$XDG_CONFIG_HOME`
+/// falls back to `~/.config`.
+pub fn config_path(tool: &str) -> Option<PathBuf> {
+    let non_empty = |var| env::var_os(var).filter(|value| !value.is_empty());
+    let base = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(base.join("rutils").join(format!("{tool}.toml")))
+}
+
+/// Returns the environment variable holding default arguments for `tool`,
+/// such as `CUTR_OPTS`.
+pub fn env_var(tool: &str) -> String {
+    format!("{}_OPTS", tool.to_uppercase())
+}
+
+/// Default arguments from one source.
+pub(crate) struct Layer {
+    source: Source,
+    /// The file or variable the arguments came from, for error messages.
+    origin: String,
+    args: Vec<OsString>,
+}
+
+/// Reads the config file and then the environment defaults of `tool`.
+pub(crate) fn layers(tool: &str) -> Result<Vec<Layer>> {
+    let mut layers = vec![];
+    if let Some(path) = config_path(tool) {
+        match fs::read_to_string(&path) {
+            Ok(text) => layers.push(Layer {
+                source: Source::ConfigFile,
+                origin: path.display().to_string(),
+                args: toml_args(&text)
+                    .map_err(|e| Error::Usage(format!("{}: {}", path.display(), e)))?,
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(Error::open(&path.display().to_string(), e)),
+        }
+    }
+
+    let var = env_var(tool);
+    if let Some(value) = env::var_os(&var) {
+        let value = value
+            .into_string()
+            .map_err(|_| Error::Usage(format!("{var}: not valid UTF-8")))?;
+        let words = shell_words::split(&value).map_err(|e| Error::Usage(format!("{var}: {e}")))?;
+        layers.push(Layer {
+            source: Source::Environment,
+            origin: var,
+            args: words.into_iter().map(Into::into).collect(),
+        });
+    }
+    Ok(layers)
+}
+
+/// Turns a config file into arguments: `key = true` gives `--key`, other
+/// values `--key=value`, and an array repeats the option for each element.
+fn toml_args(text: &str) -> std::result::Result<Vec<OsString>, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| {
+        // The first line gives the position, as in "TOML parse error at
+        // line 1, column 3".
+        let text = e.to_string();
+        let position = text.lines().next().unwrap_or_default();
+        let position = position.strip_prefix("TOML ").unwrap_or(position);
+        match e.message() {
+            "" => position.to_string(),
+            message => format!("{position}: {message}"),
+        }
+    })?;
+    let mut args = vec![];
+    for (key, value) in table {
+        let values = match value {
+            Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                Value::Boolean(true) => args.push(format!("--{key}").into()),
+                Value::Boolean(false) => {}
+                Value::String(s) => args.push(format!("--{key}={s}").into()),
+                Value::Integer(n) => args.push(format!("--{key}={n}").into()),
+                Value::Float(n) => args.push(format!("--{key}={n}").into()),
+                other => return Err(format!("unsupported value for {key}: {other}")),
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Returns `cmd` with no required arguments or groups, for parsing partial
+/// argument lists.
+pub(crate) fn relax(cmd: &Command) -> Command {
+    let groups: Vec<_> = cmd
+        .get_groups()
+        .map(|group| group.get_id().clone())
+        .collect();
+    groups
+        .into_iter()
+        .fold(cmd.clone(), |cmd, id| {
+            cmd.mut_group(id, |group| group.required(false))
+        })
+        .mut_args(|arg| arg.required(false))
+        .arg_required_else_help(false)
+}
+
+/// An option taken from a layer of defaults, as arguments that reproduce it.
+struct Setting {
+    id: String,
+    source: Source,
+    args: Vec<OsString>,
+}
+
+/// Places the defaults in `layers` beneath `args`, the command line without
+/// the program name. An option is dropped from a lower layer when a higher
+/// one gives it, or gives an option it conflicts with.
+///
+/// Returns the merged arguments and the source of each default kept.
+pub(crate) fn merge(
+    cmd: &Command,
+    layers: Vec<Layer>,
+    args: Vec<OsString>,
+) -> Result<(Vec<OsString>, HashMap<String, Source>)> {
+    let relaxed = relax(cmd);
+    let name = OsString::from(cmd.get_name());
+    let mut defaults: Vec<Setting> = vec![];
+
+    for layer in layers {
+        let matches = relaxed
+            .clone()
+            .try_get_matches_from(once(name.clone()).chain(layer.args))
+            .map_err(|e| Error::Usage(format!("{}: {}", layer.origin, clap_message(&e))))?;
+        let given = given_args(cmd, &matches);
+        if let Some(arg) = given.iter().find(|arg| arg.is_positional()) {
+            return Err(Error::Usage(format!(
+                "{}: {} cannot be given a default",
+                layer.origin,
+                arg.get_value_names()
+                    .and_then(|names| names.first())
+                    .map_or(arg.get_id().as_str(), |name| name.as_str()),
+            )));
+        }
+        defaults.retain(|default| !overridden(cmd, &given, &default.id));
+        for arg in given {
+            defaults.push(Setting {
+                id: arg.get_id().to_string(),
+                source: layer.source,
+                args: to_args(arg, &matches),
+            });
+        }
+    }
+
+    // Errors on the command line itself are left for the real parse to
+    // report, with the usual help.
+    if let Ok(matches) = relaxed.try_get_matches_from(once(name).chain(args.iter().cloned())) {
+        let exclusive = cmd.get_arguments().any(|arg| {
+            arg.is_exclusive_set()
+                && matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+        });
+        if exclusive {
+            defaults.clear();
+        }
+        let given = given_args(cmd, &matches);
+        defaults.retain(|default| !overridden(cmd, &given, &default.id));
+    }
+
+    // Defaults go first, each as a single `--long=value`, so that no option
+    // on the command line taking several values can swallow them.
+    let mut merged: Vec<_> = defaults
+        .iter()
+        .flat_map(|default| default.args.clone())
+        .collect();
+    merged.extend(args);
+
+    let sources = defaults
+        .into_iter()
+        .map(|default| (default.id, default.source))
+        .collect();
+    Ok((merged, sources))
+}
+
+/// The first line of a clap error, without its "error: " prefix.
+fn clap_message(err: &clap::Error) -> String {
+    let text = err.render().to_string();
+    let line = text.lines().next().unwrap_or_default();
+    line.strip_prefix("error: ").unwrap_or(line).to_string()
+}
+
+/// The arguments of `cmd` explicitly given in `matches`.
+fn given_args<'a>(cmd: &'a Command, matches: &ArgMatches) -> Vec<&'a Arg> {
+    cmd.get_arguments()
+        .filter(|arg| !OWN_IDS.contains(&arg.get_id().as_str()))
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect()
+}
+
+fn overridden(cmd: &Command, given: &[&Arg], id: &str) -> bool {
+    let conflicts = |a: &Arg, b: &Arg| {
+        cmd.get_arg_conflicts_with(a)
+            .iter()
+            .any(|arg| arg.get_id() == b.get_id())
+    };
+    let Some(default) = cmd.get_arguments().find(|arg| arg.get_id() == id) else {
+        return true;
+    };
+    given
+        .iter()
+        .any(|arg| arg.get_id() == id || conflicts(arg, default) || conflicts(default, arg))
+}
+
+/// Arguments giving `arg` the values it has in `matches`.
+fn to_args(arg: &Arg, matches: &ArgMatches) -> Vec<OsString> {
+    let flag = match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) => format!("--{long}"),
+        (None, Some(short)) => format!("-{short}"),
+        (None, None) => unreachable!("positional arguments have no defaults"),
+    };
+    let values: Vec<_> = if arg.get_action().takes_values() {
+        matches
+            .get_raw(arg.get_id().as_str())
+            .unwrap_or_default()
+            .collect()
+    } else {
+        vec![]
+    };
+    if values.is_empty() {
+        return vec![flag.into()];
+    }
+    values
+        .into_iter()
+        .map(|value| {
+            let mut arg = OsString::from(format!("{flag}="));
+            arg.push(value);
+            arg
+        })
+        .collect()
+}
+
+/// Writes the value of each option in `matches` as a line of TOML, with
+/// where it came from.
+pub(crate) fn print_config(
+    cmd: &Command,
+    matches: &ArgMatches,
+    sources: &HashMap<String, Source>,
+    no_config: bool,
+    out: &mut impl io::Write,
+) -> io::Result<()> {
+    let name = cmd.get_name();
+    let path = config_path(name).map_or("no config file".to_string(), |path| {
+        path.display().to_string()
+    });
+    writeln!(
+        out,
+        "# {name}: {path}, ${}{}",
+        env_var(name),
+        if no_config { " (ignored)" } else { "" }
+    )?;
+
+    for arg in cmd.get_arguments() {
+        let id = arg.get_id().as_str();
+        let (Some(long), Some(raw)) = (arg.get_long(), matches.get_raw(id)) else {
+            continue;
+        };
+        if OWN_IDS.contains(&id) {
+            continue;
+        }
+        let raw: Vec<_> = raw
+            .map(|value| value.to_string_lossy().into_owned())
+            .collect();
+        let value = if !arg.get_action().takes_values() {
+            Value::Boolean(raw.first().is_some_and(|value| value == "true"))
+        } else if matches!(arg.get_action(), ArgAction::Append) || raw.len() > 1 {
+            Value::Array(raw.into_iter().map(Value::String).collect())
+        } else {
+            Value::String(raw.into_iter().next().unwrap_or_default())
+        };
+        let source = match matches.value_source(id) {
+            Some(ValueSource::CommandLine) => sources
+                .get(id)
+                .copied()
+                .unwrap_or(Source::CommandLine)
+                .to_string(),
+            _ => "default".to_string(),
+        };
+        writeln!(out, "{long} = {value} # {source}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, toml_args, Layer, Source};
+    use clap::{Arg, ArgAction, Command};
+    use std::ffi::OsString;
+
+    fn command() -> Command {
+        Command::new("toolr")
+            .arg(Arg::new("files").num_args(1..).required(true))
+            .arg(
+                Arg::new("lines")
+                    .short('l')
+                    .long("lines")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(
+                Arg::new("bytes")
+                    .short('c')
+                    .long("bytes")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("chars"),
+            )
+            .arg(
+                Arg::new("chars")
+                    .short('m')
+                    .long("chars")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("delim").short('d').long("delim"))
+    }
+
+    fn os(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn layer(source: Source, args: &[&str]) -> Layer {
+        Layer {
+            source,
+            origin: source.to_string(),
+            args: os(args),
+        }
+    }
+
+    #[test]
+    fn test_toml_args() {
+        let args = toml_args("lines = true\nchars = false\ndelim = \",\"\nname = [\"a\", 1]\n");
+        assert_eq!(
+            args.unwrap(),
+            os(&["--delim=,", "--lines", "--name=a", "--name=1"])
+        );
+        assert!(toml_args("lines = {}").is_err());
+        assert_eq!(
+            toml_args("lines = 1\nlines = 2").unwrap_err(),
+            "parse error at line 2, column 1: duplicate key `lines` in document root"
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let layers = vec![
+            layer(Source::ConfigFile, &["--delim", ":", "-m"]),
+            layer(Source::Environment, &["-d", ",", "-l"]),
+        ];
+        let (args, sources) = merge(&command(), layers, os(&["-c", "a", "--", "-b"])).unwrap();
+        assert_eq!(args, os(&["--lines", "--delim=,", "-c", "a", "--", "-b"]));
+        assert_eq!(sources["delim"], Source::Environment);
+        assert_eq!(sources["lines"], Source::Environment);
+        assert!(!sources.contains_key("chars"));
+    }
+
+    #[test]
+    fn test_merge_errors() {
+        let layers = vec![layer(Source::Environment, &["--bogus"])];
+        let err = merge(&command(), layers, vec![]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "environment: unexpected argument '--bogus' found"
+        );
+
+        let layers = vec![layer(Source::ConfigFile, &["a.txt"])];
+        assert!(merge(&command(), layers, vec![]).is_err());
+    }
+}
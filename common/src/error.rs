@@ -0,0 +1,187 @@
+use std::{fmt, io, process};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// An input or output file could not be opened.
+    Open { path: String, source: io::Error },
+    /// An input failed part way through being read.
+    Read { path: String, source: io::Error },
+    /// Writing the output failed.
+    Write(io::Error),
+    /// The arguments were invalid.
+    Usage(String),
+    /// Some inputs failed; each has already been reported on stderr.
+    Partial { failed: usize },
+}
+
+impl Error {
+    pub fn open(path: &str, source: io::Error) -> Self {
+        Self::Open {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    pub fn read(path: &str, source: io::Error) -> Self {
+        Self::Read {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    /// The process exit status for this error, following coreutils:
+    /// 2 for usage errors and 1 for everything else.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Usage(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// True when the reader on the other end of stdout went away, which is
+    /// how `catr big.log | headr -n 1` normally ends.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, Self::Write(e) if e.kind() == io::ErrorKind::BrokenPipe)
+    }
+
+    /// Reports the error on stderr, unless that already happened, and exits.
+    /// A broken pipe is not an error and exits quietly with status 0.
+    pub fn exit(self) -> ! {
+        if self.is_broken_pipe() {
+            process::exit(0)
+        }
+        if !matches!(self, Self::Partial { .. }) {
+            eprintln!("{}", self);
+        }
+        process::exit(self.exit_code())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open { path, source } | Self::Read { path, source } => {
+                write!(f, "{}: {}", path, source)
+            }
+            Self::Write(source) => write!(f, "write error: {}", source),
+            Self::Usage(msg) => write!(f, "{}", msg),
+            Self::Partial { failed } => write!(f, "{} input(s) failed", failed),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Open { source, .. } | Self::Read { source, .. } | Self::Write(source) => {
+                Some(source)
+            }
+            Self::Usage(_) | Self::Partial { .. } => None,
+        }
+    }
+}
+
+/// Output errors are the only I/O errors propagated with a bare `?`; input
+/// errors go through [`Error::open`] and [`Error::read`] to keep the path.
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Self::Write(source)
+    }
+}
+
+/// Tracks per-input failures so a tool can keep going and still exit 1.
+#[derive(Debug, Default)]
+pub struct Failures {
+    failed: usize,
+}
+
+impl Failures {
+    /// Reports `err` on stderr and counts it so the caller can move on to the
+    /// next input. Write errors are handed back instead: once the output is
+    /// gone there is nothing left to move on to.
+    pub fn report(&mut self, err: Error) -> Result<()> {
+        if let Error::Write(_) = err {
+            return Err(err);
+        }
+        eprintln!("{}", err);
+        self.failed += 1;
+        Ok(())
+    }
+
+    /// Reports `msg` on stderr and counts it, for a failure that is not an
+    /// [`Error`] of its own.
+    pub fn fail(&mut self, msg: &str) {
+        eprintln!("{}", msg);
+        self.failed += 1;
+    }
+
+    /// Reports a problem on stderr as a warning, without counting it as a
+    /// failure.
+    pub fn warn(msg: &str) {
+        eprintln!("warning: {}", msg);
+    }
+
+    pub fn finish(self) -> Result<()> {
+        match self.failed {
+            0 => Ok(()),
+            failed => Err(Error::Partial { failed }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, Failures};
+    use std::io;
+
+    #[test]
+    fn test_exit_code() {
+        let not_found = || io::Error::from(io::ErrorKind::NotFound);
+        assert_eq!(Error::open("foo", not_found()).exit_code(), 1);
+        assert_eq!(Error::read("foo", not_found()).exit_code(), 1);
+        assert_eq!(Error::from(not_found()).exit_code(), 1);
+        assert_eq!(Error::Partial { failed: 2 }.exit_code(), 1);
+        assert_eq!(Error::Usage("bad".to_string()).exit_code(), 2);
+    }
+
+    #[test]
+    fn test_display() {
+        let err = Error::open("foo", io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(err.to_string(), "foo: gone");
+        assert_eq!(Error::Usage("bad".to_string()).to_string(), "bad");
+    }
+
+    #[test]
+    fn test_failures() {
+        assert!(Failures::default().finish().is_ok());
+
+        let mut failures = Failures::default();
+        failures.fail("failed");
+        assert!(matches!(
+            failures.finish(),
+            Err(Error::Partial { failed: 1 })
+        ));
+
+        let mut failures = Failures::default();
+        assert!(failures.report(Error::Usage("bad".to_string())).is_ok());
+        assert!(matches!(
+            failures.finish(),
+            Err(Error::Partial { failed: 1 })
+        ));
+
+        let mut failures = Failures::default();
+        let broken = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(failures.report(Error::from(broken)).is_err());
+        assert!(failures.finish().is_ok());
+    }
+
+    #[test]
+    fn test_is_broken_pipe() {
+        let broken = || io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(Error::from(broken()).is_broken_pipe());
+        assert!(!Error::read("foo", broken()).is_broken_pipe());
+        assert!(!Error::from(io::Error::from(io::ErrorKind::Other)).is_broken_pipe());
+    }
+}
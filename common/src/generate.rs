@@ -0,0 +1,79 @@
+use std::io::{self, Write};
+
+use clap::{builder::PossibleValue, Arg, Command, ValueEnum};
+use clap_complete::Shell;
+use clap_mangen::Man;
+
+/// What the hidden `--generate` option writes: a shell completion script or
+/// a roff man page.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Generate {
+    Shell(Shell),
+    Man,
+}
+
+impl ValueEnum for Generate {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Shell(Shell::Bash),
+            Self::Shell(Shell::Zsh),
+            Self::Shell(Shell::Fish),
+            Self::Shell(Shell::Elvish),
+            Self::Shell(Shell::PowerShell),
+            Self::Man,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            Self::Shell(shell) => shell.to_possible_value(),
+            Self::Man => Some(PossibleValue::new("man")),
+        }
+    }
+}
+
+/// The hidden `--generate <TARGET>` option.
+pub(crate) fn arg() -> Arg {
+    Arg::new("generate")
+        .long("generate")
+        .value_name("TARGET")
+        .value_parser(clap::value_parser!(Generate))
+        .exclusive(true)
+        .hide(true)
+}
+
+/// Writes `target` for `cmd` to `out`.
+pub fn generate(target: Generate, cmd: &mut Command, out: &mut dyn Write) -> io::Result<()> {
+    match target {
+        Generate::Shell(shell) => {
+            let name = cmd.get_name().to_string();
+            clap_complete::generate(shell, cmd, name, out);
+            Ok(())
+        }
+        Generate::Man => Man::new(cmd.clone()).render(out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, Generate};
+    use clap::{Arg, Command};
+    use clap_complete::Shell;
+
+    fn command() -> Command {
+        Command::new("toolr").arg(Arg::new("files").long("file").required(true))
+    }
+
+    #[test]
+    fn test_generate() {
+        let mut out = Vec::new();
+        assert!(generate(Generate::Shell(Shell::Bash), &mut command(), &mut out).is_ok());
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("_toolr()"));
+        assert!(text.contains("--file"));
+
+        let mut out = Vec::new();
+        assert!(generate(Generate::Man, &mut command(), &mut out).is_ok());
+        assert!(String::from_utf8(out).unwrap().starts_with(".ie"));
+    }
+}
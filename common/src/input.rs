@@ -0,0 +1,180 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+};
+
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use xz2::read::XzDecoder;
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+use crate::archive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    fn detect(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0x1f, 0x8b, ..] => Some(Self::Gzip),
+            [b'B', b'Z', b'h', ..] => Some(Self::Bzip2),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Self::Xz),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves the input names in a tool's `Config` to readers.
+///
+/// The command-line tools pass [`open`]; embedders can pass any closure, for
+/// instance one serving in-memory buffers.
+pub trait Inputs {
+    fn open(&mut self, name: &str) -> io::Result<Box<dyn BufRead>>;
+}
+
+impl<F> Inputs for F
+where
+    F: FnMut(&str) -> io::Result<Box<dyn BufRead>>,
+{
+    fn open(&mut self, name: &str) -> io::Result<Box<dyn BufRead>> {
+        self(name)
+    }
+}
+
+/// Opens `filename` for reading, or stdin when it is "-".
+///
+/// Gzip, bzip2, xz and zstd streams are recognized by their magic bytes and
+/// decompressed on the fly; anything else is passed through unchanged.
+/// A name such as `bundle.tar.gz//data/users.tsv` that does not exist
+/// itself opens a member of a tar or zip archive; see [`archive::split`].
+pub fn open(filename: &str) -> io::Result<Box<dyn BufRead>> {
+    if filename == "-" {
+        return decompress(io::stdin());
+    }
+    match archive::split(filename) {
+        Some((archive, member)) => decompress(archive::open_member(archive, member)?),
+        None => decompress(File::open(filename)?),
+    }
+}
+
+/// An input as [`open_plain`] found it.
+#[cfg(unix)]
+pub enum Plain {
+    /// A file, or stdin, whose bytes are read as they are. What was read
+    /// while looking for a compression magic number is still buffered.
+    File(BufReader<File>),
+    /// A compressed stream or archive member, decoded as by [`open`].
+    Decoded(Box<dyn BufRead>),
+}
+
+/// Opens `filename` like [`open`], but hands back the file itself when its
+/// bytes need no decoding, so a caller copying it whole can leave the work
+/// to the kernel. Stdin is duplicated rather than taken over.
+#[cfg(unix)]
+pub fn open_plain(filename: &str) -> io::Result<Plain> {
+    use std::os::fd::AsFd;
+
+    let file = if filename == "-" {
+        File::from(io::stdin().as_fd().try_clone_to_owned()?)
+    } else if archive::split(filename).is_some() {
+        return open(filename).map(Plain::Decoded);
+    } else {
+        File::open(filename)?
+    };
+    let mut reader = BufReader::new(file);
+    match Compression::detect(reader.fill_buf()?) {
+        None => Ok(Plain::File(reader)),
+        Some(_) => decompress(reader).map(Plain::Decoded),
+    }
+}
+
+pub(crate) fn decompress(inner: impl Read + 'static) -> io::Result<Box<dyn BufRead>> {
+    // Only peek at what the first read returns so interactive stdin is not
+    // held up waiting for a full magic number.
+    let mut reader = BufReader::new(inner);
+    let compression = Compression::detect(reader.fill_buf()?);
+
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Compression::Bzip2) => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Some(Compression::Xz) => Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))),
+        Some(Compression::Zstd) => Box::new(BufReader::new(ZstdDecoder::with_buffer(reader)?)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress, Compression};
+    use std::io::{Cursor, Read, Write};
+
+    const TEXT: &str = "The quick brown fox\njumps over the lazy dog.\n";
+
+    fn read_all(data: Vec<u8>) -> String {
+        let mut out = String::new();
+        decompress(Cursor::new(data))
+            .unwrap()
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            Compression::detect(&[0x1f, 0x8b, 0x08]),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(Compression::detect(b"BZh91AY"), Some(Compression::Bzip2));
+        assert_eq!(
+            Compression::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+            Some(Compression::Xz)
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::detect(b""), None);
+        assert_eq!(Compression::detect(b"BZ"), None);
+        assert_eq!(Compression::detect(TEXT.as_bytes()), None);
+    }
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(read_all(TEXT.as_bytes().to_vec()), TEXT);
+        assert_eq!(read_all(vec![]), "");
+    }
+
+    #[test]
+    fn test_gzip() {
+        let mut enc = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(read_all(enc.finish().unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_bzip2() {
+        let mut enc = bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(read_all(enc.finish().unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_xz() {
+        let mut enc = xz2::write::XzEncoder::new(vec![], 6);
+        enc.write_all(TEXT.as_bytes()).unwrap();
+        assert_eq!(read_all(enc.finish().unwrap()), TEXT);
+    }
+
+    #[test]
+    fn test_zstd() {
+        let data = zstd::encode_all(TEXT.as_bytes(), 0).unwrap();
+        assert_eq!(read_all(data), TEXT);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+};
+
+use clap::{Arg, ArgAction, ArgMatches};
+use serde::Serialize;
+
+/// How a tool writes its results. The JSON schemas are described in
+/// `docs/json.md`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    /// The traditional whitespace-aligned text.
+    #[default]
+    Text,
+    /// A single JSON array of objects.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+impl Format {
+    /// The `--json` and `--ndjson` flags shared by the tools.
+    pub fn args() -> [Arg; 2] {
+        [
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("ndjson")
+                .help("Write results as a JSON array"),
+            Arg::new("ndjson")
+                .long("ndjson")
+                .action(ArgAction::SetTrue)
+                .help("Write results as one JSON object per line"),
+        ]
+    }
+
+    /// Reads the flags added by [`Format::args`].
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        if matches.get_flag("json") {
+            Self::Json
+        } else if matches.get_flag("ndjson") {
+            Self::Ndjson
+        } else {
+            Self::Text
+        }
+    }
+
+    pub fn is_text(self) -> bool {
+        self == Self::Text
+    }
+}
+
+/// Writes a stream of objects to an output as either a JSON array or
+/// NDJSON. The array is only closed by [`JsonWriter::finish`].
+#[derive(Debug)]
+pub struct JsonWriter {
+    format: Format,
+    written: usize,
+}
+
+impl JsonWriter {
+    pub fn new(format: Format) -> Self {
+        Self { format, written: 0 }
+    }
+
+    pub fn write(&mut self, out: &mut impl Write, value: &impl Serialize) -> io::Result<()> {
+        if self.format == Format::Json {
+            out.write_all(if self.written == 0 { b"[\n" } else { b",\n" })?;
+        }
+        serde_json::to_writer(&mut *out, value)?;
+        if self.format == Format::Ndjson {
+            out.write_all(b"\n")?;
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(self, out: &mut impl Write) -> io::Result<()> {
+        if self.format == Format::Json {
+            out.write_all(if self.written == 0 { b"[]\n" } else { b"\n]\n" })?;
+        }
+        Ok(())
+    }
+}
+
+/// The `{"file", "record", "fields"}` object written by headr and cutr.
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    pub file: &'a str,
+    /// 1-based position of the record in its file.
+    pub record: u64,
+    pub fields: Vec<Cow<'a, str>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, JsonWriter, Record};
+    use std::borrow::Cow;
+
+    fn write(format: Format, n: u64) -> String {
+        let (mut writer, mut out) = (JsonWriter::new(format), Vec::new());
+        for record in 1..=n {
+            let fields = vec![Cow::from("a\tb")];
+            let value = Record {
+                file: "-",
+                record,
+                fields,
+            };
+            writer.write(&mut out, &value).unwrap();
+        }
+        writer.finish(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_json_writer() {
+        assert_eq!(write(Format::Json, 0), "[]\n");
+        assert_eq!(
+            write(Format::Json, 2),
+            "[\n{\"file\":\"-\",\"record\":1,\"fields\":[\"a\\tb\"]},\n\
+            {\"file\":\"-\",\"record\":2,\"fields\":[\"a\\tb\"]}\n]\n"
+        );
+        assert_eq!(write(Format::Ndjson, 0), "");
+        assert_eq!(
+            write(Format::Ndjson, 1),
+            "{\"file\":\"-\",\"record\":1,\"fields\":[\"a\\tb\"]}\n"
+        );
+    }
+}
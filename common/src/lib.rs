@@ -0,0 +1,26 @@
+pub mod archive;
+mod cli;
+mod compat;
+mod defaults;
+mod error;
+mod generate;
+mod input;
+mod json;
+mod number;
+mod output;
+mod records;
+#[cfg(feature = "test-support")]
+pub mod testing;
+
+pub use cli::get_matches_from;
+pub use compat::Compat;
+pub use defaults::{config_path, env_var};
+pub use error::{Error, Failures, Result};
+pub use generate::{generate, Generate};
+pub use input::{open, Inputs};
+#[cfg(unix)]
+pub use input::{open_plain, Plain};
+pub use json::{Format, JsonWriter, Record};
+pub use number::{Justify, NumberFormat};
+pub use output::stdout;
+pub use records::{terminator, zero_terminated_arg, Records, NEWLINE, NUL};
@@ -0,0 +1,106 @@
+use std::io::{self, Write};
+
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// Where a line number sits in its field, named as `nl -n` names them.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Justify {
+    /// `ln`: left justified.
+    Left,
+    /// `rn`: right justified.
+    Right,
+    /// `rz`: right justified with leading zeros.
+    RightZero,
+}
+
+impl ValueEnum for Justify {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Left, Self::Right, Self::RightZero]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Left => PossibleValue::new("ln").help("Left justified"),
+            Self::Right => PossibleValue::new("rn").help("Right justified"),
+            Self::RightZero => PossibleValue::new("rz").help("Right justified with leading zeros"),
+        })
+    }
+}
+
+/// How line numbers are written: justified in a field of `width`
+/// characters and followed by `separator`. The default is `cat -n`'s
+/// `%6d\t`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NumberFormat {
+    pub justify: Justify,
+    pub width: usize,
+    pub separator: Vec<u8>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            justify: Justify::Right,
+            width: 6,
+            separator: b"\t".to_vec(),
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Writes `number` in its field, then the separator. A number wider
+    /// than the field is written whole.
+    pub fn write(&self, number: i64, out: &mut impl Write) -> io::Result<()> {
+        let width = self.width;
+        match self.justify {
+            Justify::Left => write!(out, "{number:<width$}")?,
+            Justify::Right => write!(out, "{number:>width$}")?,
+            Justify::RightZero => write!(out, "{number:0width$}")?,
+        }
+        out.write_all(&self.separator)
+    }
+
+    /// Writes the spaces that take the place of a number and separator on
+    /// a line that is not numbered.
+    pub fn write_blank(&self, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{:1$}", "", self.width + self.separator.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Justify, NumberFormat};
+
+    fn run(format: &NumberFormat, number: Option<i64>) -> String {
+        let mut out = Vec::new();
+        match number {
+            Some(number) => format.write(number, &mut out).unwrap(),
+            None => format.write_blank(&mut out).unwrap(),
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_number_format() {
+        let format = NumberFormat::default();
+        assert_eq!(run(&format, Some(12)), "    12\t");
+        assert_eq!(run(&format, None), "       ");
+        assert_eq!(run(&format, Some(1234567)), "1234567\t");
+
+        let format = NumberFormat {
+            justify: Justify::Left,
+            width: 3,
+            separator: b": ".to_vec(),
+        };
+        assert_eq!(run(&format, Some(7)), "7  : ");
+        assert_eq!(run(&format, None), "     ");
+
+        let format = NumberFormat {
+            justify: Justify::RightZero,
+            width: 4,
+            separator: vec![],
+        };
+        assert_eq!(run(&format, Some(7)), "0007");
+        assert_eq!(run(&format, Some(-7)), "-007");
+    }
+}
@@ -0,0 +1,15 @@
+use std::io::{self, BufWriter, IsTerminal, Write};
+
+/// Returns a locked handle on stdout for the lifetime of the process.
+///
+/// Output is block buffered unless stdout is a terminal, where the line
+/// buffering of [`io::Stdout`] is kept so interactive use stays responsive.
+/// Callers must `flush` before returning so write errors are not lost on drop.
+pub fn stdout() -> Box<dyn Write> {
+    let out = io::stdout().lock();
+    if out.is_terminal() {
+        Box::new(out)
+    } else {
+        Box::new(BufWriter::new(out))
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::{self, BufRead};
+
+use clap::{Arg, ArgAction};
+
+/// Separates records by default.
+pub const NEWLINE: u8 = b'\n';
+/// Separates records with `-z`, so that names containing newlines survive.
+pub const NUL: u8 = b'\0';
+
+/// Returns the record terminator for a tool's `-z` flag.
+pub fn terminator(zero_terminated: bool) -> u8 {
+    if zero_terminated {
+        NUL
+    } else {
+        NEWLINE
+    }
+}
+
+/// The `-z/--zero-terminated` flag shared by the record-oriented tools.
+pub fn zero_terminated_arg() -> Arg {
+    Arg::new("zero_terminated")
+        .short('z')
+        .long("zero-terminated")
+        .action(ArgAction::SetTrue)
+        .help("Records are separated by NUL, not newline")
+}
+
+/// Splits input into records ending in `terminator`, the generalization of
+/// `read_until(b'\n', ..)` that every tool reads through.
+pub struct Records<R> {
+    inner: R,
+    terminator: u8,
+}
+
+impl<R: BufRead> Records<R> {
+    pub fn new(inner: R, terminator: u8) -> Self {
+        Self { inner, terminator }
+    }
+
+    pub fn terminator(&self) -> u8 {
+        self.terminator
+    }
+
+    /// Replaces the contents of `record` with the next record, terminator
+    /// included. The last record may lack one. Returns false at end of input.
+    pub fn read(&mut self, record: &mut Vec<u8>) -> io::Result<bool> {
+        record.clear();
+        Ok(self.inner.read_until(self.terminator, record)? > 0)
+    }
+
+    /// Returns `record` without its terminator.
+    pub fn strip<'a>(&self, record: &'a [u8]) -> &'a [u8] {
+        record.strip_suffix(&[self.terminator]).unwrap_or(record)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Records, NEWLINE, NUL};
+    use std::io::Cursor;
+
+    fn collect(input: &[u8], terminator: u8) -> Vec<Vec<u8>> {
+        let mut records = Records::new(Cursor::new(input), terminator);
+        let mut record = Vec::new();
+        let mut all = vec![];
+        while records.read(&mut record).unwrap() {
+            all.push(record.clone());
+        }
+        all
+    }
+
+    #[test]
+    fn test_read() {
+        assert_eq!(collect(b"a\nb\0c\n", NEWLINE), [&b"a\n"[..], b"b\0c\n"]);
+        assert_eq!(collect(b"a\nb\0c\n", NUL), [&b"a\nb\0"[..], b"c\n"]);
+        assert_eq!(collect(b"", NUL), Vec::<Vec<u8>>::new());
+    }
+
+    #[test]
+    fn test_strip() {
+        let records = Records::new(Cursor::new(""), NUL);
+        assert_eq!(records.strip(b"a\n\0"), b"a\n");
+        assert_eq!(records.strip(b"a\n"), b"a\n");
+    }
+}
@@ -0,0 +1,83 @@
+//! Helpers shared by the tools' integration tests, built with the
+//! `test-support` feature.
+
+use std::{
+    error::Error,
+    ffi::OsStr,
+    fs,
+    io::Read,
+    process::{self, Stdio},
+};
+
+use assert_cmd::{cargo::cargo_bin, Command};
+use toml::Value;
+
+/// The `--compat` profiles every corpus case gives expected output for.
+const PROFILES: [&str; 3] = ["gnu", "bsd", "posix"];
+
+/// Runs every case in the TOML `corpus` through the binary `program` under
+/// each profile, and reports all the differences at once.
+///
+/// Each `[[case]]` has a `name`, `args`, an optional `stdin`, and an
+/// `expected` table holding the output for each profile.
+pub fn compat_corpus(program: &str, corpus: &str) -> Result<(), Box<dyn Error>> {
+    let corpus: toml::Table = fs::read_to_string(corpus)?.parse()?;
+    let cases = corpus["case"].as_array().ok_or("no cases")?;
+    let mut failures = vec![];
+    for case in cases {
+        let name = case["name"].as_str().ok_or("case without a name")?;
+        let args: Vec<_> = case["args"]
+            .as_array()
+            .ok_or("case without args")?
+            .iter()
+            .filter_map(Value::as_str)
+            .collect();
+        let stdin = case.get("stdin").and_then(Value::as_str).unwrap_or("");
+        for profile in PROFILES {
+            let expected = case["expected"]
+                .get(profile)
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("{name}: no expected output for {profile}"))?;
+            let output = Command::cargo_bin(program)?
+                .args(["--no-config", "--compat", profile])
+                .args(&args)
+                .write_stdin(stdin)
+                .output()?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if stdout != expected {
+                failures.push(format!(
+                    "{name} --compat {profile}:\n  expected {expected:?}\n  got      {stdout:?}"
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+    Ok(())
+}
+
+/// Runs the binary `program` with `args` and closes its stdout after the
+/// first byte, as `head -c 1` would. The program must then exit
+/// successfully and quietly, however much it had left to write.
+pub fn run_closed_pipe<I, S>(program: &str, args: I) -> Result<(), Box<dyn Error>>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut child = process::Command::new(cargo_bin(program))
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdout = child.stdout.take().ok_or("no stdout")?;
+    stdout.read_exact(&mut [0; 1])?;
+    drop(stdout);
+
+    let output = child.wait_with_output()?;
+    assert!(
+        output.status.success(),
+        "{program} exited with {}",
+        output.status
+    );
+    assert_eq!(String::from_utf8(output.stderr)?, "");
+    Ok(())
+}
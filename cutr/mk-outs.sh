@@ -5,6 +5,7 @@ set -u
 CSV="tests/inputs/movies1.csv"
 TSV="tests/inputs/movies1.tsv"
 BOOKS="tests/inputs/books.tsv"
+LATIN1="tests/inputs/latin1.tsv"
 OUT_DIR="tests/expected"
 
 [[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
@@ -26,3 +27,6 @@ for POS in 1 2 8 1-2 2-3 1-8; do
 done
 
 echo -e "AA\nÉÉ\nSS\nJJ" > "$OUT_DIR/books.c1,1.out"
+
+cut -f 2 $LATIN1 > "$OUT_DIR/$(basename $LATIN1).f2.out"
+cut -b 4 $LATIN1 > "$OUT_DIR/$(basename $LATIN1).b4.out"
@@ -1,15 +1,17 @@
 use clap::{Arg, Command};
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use common::{Compat, Error, Failures, Format, Inputs, JsonWriter, Record, Records, Result};
+use csv::{ByteRecord, ReaderBuilder, Terminator, WriterBuilder};
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    borrow::Cow,
+    env,
+    ffi::OsString,
+    io::{self, BufRead, Write},
     num::NonZeroUsize,
     ops::Range,
 };
 
-type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
-type PositionList = Vec<Range<usize>>;
+pub type PositionList = Vec<Range<usize>>;
 
 #[derive(Debug)]
 pub enum Extract {
@@ -23,12 +25,113 @@ pub struct Config {
     files: Vec<String>,
     delimiter: u8,
     extract: Extract,
+    terminator: u8,
+    format: Format,
+    compat: Option<Compat>,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("cutr")
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    delimiter: u8,
+    extract: Option<Extract>,
+    zero_terminated: bool,
+    format: Format,
+    compat: Option<Compat>,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            delimiter: b'\t',
+            extract: None,
+            zero_terminated: false,
+            format: Format::Text,
+            compat: None,
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Field delimiter, a tab by default.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// What to cut from each line; see [`parse_pos`] for building the list.
+    pub fn extract(mut self, extract: Extract) -> Self {
+        self.extract = Some(extract);
+        self
+    }
+
+    /// Separate records by NUL instead of newline.
+    pub fn zero_terminated(mut self, zero_terminated: bool) -> Self {
+        self.zero_terminated = zero_terminated;
+        self
+    }
+
+    /// Output format. In JSON, `-b` and `-c` give a single field per record.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Cut as another implementation does, without CSV quoting; see
+    /// `docs/compat.md`.
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        let extract = self
+            .extract
+            .ok_or_else(|| Error::Usage("Must have --fields, --bytes, or --chars".to_string()))?;
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            delimiter: self.delimiter,
+            extract,
+            terminator: common::terminator(self.zero_terminated),
+            format: self.format,
+            compat: self.compat,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("cutr")
         .author("Talentaa <talentaa@qq.com>")
         .version("0.1.0")
+        .about("Rust cut")
+        .after_help(
+            "BYTES, CHARS and FIELDS are comma-separated lists of positions \
+            counted from 1, or ranges of them such as 3-5, e.g. \"1,3-5\".",
+        )
         .arg(
             Arg::new("files")
                 .value_name("FILE")
@@ -69,12 +172,26 @@ pub fn get_args() -> MyResult<Config> {
                 .help("Selected fields")
                 .conflicts_with_all(["chars", "bytes"]),
         )
-        .get_matches();
+        .arg(common::zero_terminated_arg())
+        .args(Format::args())
+        .arg(Compat::arg())
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
 
     let delimiter: &String = matches.get_one("delimiter").unwrap();
     let delim_bytes = delimiter.as_bytes();
     if delim_bytes.len() != 1 {
-        return Err(From::from(format!(
+        return Err(Error::Usage(format!(
             "--delim \"{}\" must be a single byte",
             delimiter
         )));
@@ -99,63 +216,216 @@ pub fn get_args() -> MyResult<Config> {
         .transpose()?;
 
     let extract = if let Some(field_pos) = fields {
-        Extract::Fields(field_pos)
+        Some(Extract::Fields(field_pos))
     } else if let Some(byte_pos) = bytes {
-        Extract::Bytes(byte_pos)
-    } else if let Some(char_pos) = chars {
-        Extract::Chars(char_pos)
+        Some(Extract::Bytes(byte_pos))
     } else {
-        return Err(From::from("Must have --fields, --bytes, or --chars"));
+        chars.map(Extract::Chars)
     };
 
-    Ok(Config {
-        files: matches
-            .get_many("files")
-            .expect("files required")
-            .cloned()
-            .collect(),
-        delimiter: *delim_bytes.first().unwrap(),
-        extract,
-    })
+    let mut builder = Config::builder()
+        .files(
+            matches
+                .get_many::<String>("files")
+                .expect("files required")
+                .cloned(),
+        )
+        .delimiter(delim_bytes[0])
+        .zero_terminated(matches.get_flag("zero_terminated"))
+        .format(Format::from_matches(&matches))
+        .compat(Compat::from_matches(&matches));
+    if let Some(extract) = extract {
+        builder = builder.extract(extract);
+    }
+    builder.build()
+}
+
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::open, common::stdout())
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+/// Writes the selected parts of each of `config`'s files, opened through
+/// `inputs`, to `out`.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, mut out: impl Write) -> Result<()> {
+    let mut failures = Failures::default();
+    let mut json = JsonWriter::new(config.format);
     for filename in &config.files {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => match &config.extract {
-                Extract::Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(config.delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-
-                    let mut wtr = WriterBuilder::new()
-                        .delimiter(config.delimiter)
-                        .from_writer(io::stdout());
-
-                    for record in reader.records() {
-                        let record = record?;
-                        wtr.write_record(extract_fields(&record, field_pos))?;
-                    }
+        if let Err(err) = cut_file(filename, config, &mut inputs, &mut json, &mut out) {
+            failures.report(err)?;
+        }
+    }
+    json.finish(&mut out)?;
+    out.flush()?;
+    failures.finish()
+}
+
+fn cut_file(
+    filename: &str,
+    config: &Config,
+    inputs: &mut impl Inputs,
+    json: &mut JsonWriter,
+    out: &mut impl Write,
+) -> Result<()> {
+    let file = inputs
+        .open(filename)
+        .map_err(|e| Error::open(filename, e))?;
+    if let Some(compat) = config.compat {
+        return cut_plain(file, filename, config, compat, json, out);
+    }
+    let read_err = |e| Error::read(filename, e);
+
+    match &config.extract {
+        Extract::Fields(field_pos) => {
+            let mut reader = ReaderBuilder::new();
+            let mut writer = WriterBuilder::new();
+            if config.terminator != common::NEWLINE {
+                reader.terminator(Terminator::Any(config.terminator));
+                writer.terminator(Terminator::Any(config.terminator));
+            }
+            let mut reader = reader
+                .delimiter(config.delimiter)
+                .has_headers(false)
+                .from_reader(file);
+
+            if config.format.is_text() {
+                let mut wtr = writer.delimiter(config.delimiter).from_writer(&mut *out);
+                for record in reader.byte_records() {
+                    let record = record.map_err(|e| read_err(e.into()))?;
+                    wtr.write_record(extract_fields(&record, field_pos))
+                        .map_err(io::Error::from)?;
                 }
-                Extract::Bytes(byte_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos))
-                    }
+                wtr.flush()?;
+            } else {
+                for (record, num) in reader.byte_records().zip(1..) {
+                    let record = record.map_err(|e| read_err(e.into()))?;
+                    let fields = extract_fields(&record, field_pos)
+                        .into_iter()
+                        .map(String::from_utf8_lossy)
+                        .collect();
+                    write_record(json, out, filename, num, fields)?;
                 }
-                Extract::Chars(char_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos))
-                    }
+            }
+        }
+        Extract::Bytes(byte_pos) => {
+            let mut records = Records::new(file, config.terminator);
+            let mut line = Vec::new();
+            let mut num = 0;
+            while records.read(&mut line).map_err(read_err)? {
+                num += 1;
+                let bytes = extract_bytes(records.strip(&line), byte_pos);
+                if config.format.is_text() {
+                    out.write_all(&bytes)?;
+                    out.write_all(&[config.terminator])?;
+                } else {
+                    let field = String::from_utf8_lossy(&bytes).into_owned();
+                    write_record(json, out, filename, num, vec![field.into()])?;
                 }
-            },
+            }
+        }
+        Extract::Chars(char_pos) => {
+            let mut records = Records::new(file, config.terminator);
+            let mut line = Vec::new();
+            let mut num = 0;
+            while records.read(&mut line).map_err(read_err)? {
+                num += 1;
+                let text = String::from_utf8_lossy(records.strip(&line));
+                let chars = extract_chars(&text, char_pos);
+                if config.format.is_text() {
+                    out.write_all(chars.as_bytes())?;
+                    out.write_all(&[config.terminator])?;
+                } else {
+                    write_record(json, out, filename, num, vec![chars.into()])?;
+                }
+            }
         }
     }
     Ok(())
 }
 
-fn parse_pos(range: &str) -> MyResult<PositionList> {
+/// Cuts `file` as other implementations do: fields are split at every
+/// delimiter, ignoring quotes, a line without the delimiter is written
+/// whole, and positions are written once each, in input order. GNU cut
+/// treats characters as bytes.
+fn cut_plain(
+    file: impl BufRead,
+    filename: &str,
+    config: &Config,
+    compat: Compat,
+    json: &mut JsonWriter,
+    out: &mut impl Write,
+) -> Result<()> {
+    let selected = |pos: &[Range<usize>], i: usize| pos.iter().any(|range| range.contains(&i));
+    let mut records = Records::new(file, config.terminator);
+    let mut line = Vec::new();
+    let mut num = 0;
+    while records
+        .read(&mut line)
+        .map_err(|e| Error::read(filename, e))?
+    {
+        num += 1;
+        let record = records.strip(&line);
+        let fields: Vec<Cow<[u8]>> = match &config.extract {
+            Extract::Fields(_) if !record.contains(&config.delimiter) => vec![record.into()],
+            Extract::Fields(field_pos) => record
+                .split(|&b| b == config.delimiter)
+                .enumerate()
+                .filter(|(i, _)| selected(field_pos, *i))
+                .map(|(_, field)| field.into())
+                .collect(),
+            Extract::Chars(char_pos) if compat != Compat::Gnu => {
+                let text = String::from_utf8_lossy(record);
+                let chars = text
+                    .chars()
+                    .enumerate()
+                    .filter(|(i, _)| selected(char_pos, *i))
+                    .map(|(_, c)| c)
+                    .collect::<String>();
+                vec![chars.into_bytes().into()]
+            }
+            Extract::Bytes(pos) | Extract::Chars(pos) => {
+                let bytes = record
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| selected(pos, *i))
+                    .map(|(_, &b)| b)
+                    .collect::<Vec<_>>();
+                vec![bytes.into()]
+            }
+        };
+
+        if config.format.is_text() {
+            out.write_all(&fields.join(&config.delimiter))?;
+            out.write_all(&[config.terminator])?;
+        } else {
+            let fields = fields
+                .iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned().into())
+                .collect();
+            write_record(json, out, filename, num, fields)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_record(
+    json: &mut JsonWriter,
+    out: &mut impl Write,
+    file: &str,
+    record: u64,
+    fields: Vec<Cow<'_, str>>,
+) -> io::Result<()> {
+    json.write(
+        out,
+        &Record {
+            file,
+            record,
+            fields,
+        },
+    )
+}
+
+/// Parses a list such as "1,3-5" into zero-based, half-open ranges.
+pub fn parse_pos(range: &str) -> Result<PositionList> {
     let range_re = Regex::new(r"^(\d+)-(\d+)$").unwrap();
     range
         .split(',')
@@ -176,11 +446,12 @@ fn parse_pos(range: &str) -> MyResult<PositionList> {
                 })
             })
         })
-        .collect::<Result<_, _>>()
-        .map_err(From::from)
+        .collect::<std::result::Result<_, _>>()
+        .map_err(Error::Usage)
 }
 
-fn parse_index(input: &str) -> Result<usize, String> {
+#[allow(clippy::obfuscated_if_else)]
+fn parse_index(input: &str) -> std::result::Result<usize, String> {
     let value_error = || format!("illegal list value: \"{}\"", input);
     input
         .starts_with('+')
@@ -193,13 +464,6 @@ fn parse_index(input: &str) -> Result<usize, String> {
         })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
-}
-
 fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
     let chars: Vec<_> = line.chars().collect();
     char_pos
@@ -209,17 +473,15 @@ fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
         .collect()
 }
 
-fn extract_bytes(line: &str, byte_pos: &[Range<usize>]) -> String {
-    let bytes: Vec<_> = line.bytes().collect();
-    let selected: Vec<_> = byte_pos
+fn extract_bytes(line: &[u8], byte_pos: &[Range<usize>]) -> Vec<u8> {
+    byte_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
-        .collect();
-    String::from_utf8_lossy(&selected).into_owned()
+        .flat_map(|range| range.filter_map(|i| line.get(i)).copied())
+        .collect()
 }
 
-fn extract_fields<'a>(record: &'a StringRecord, field_pos: &[Range<usize>]) -> Vec<&'a str> {
+fn extract_fields<'a>(record: &'a ByteRecord, field_pos: &[Range<usize>]) -> Vec<&'a [u8]> {
     field_pos
         .iter()
         .cloned()
@@ -228,11 +490,14 @@ fn extract_fields<'a>(record: &'a StringRecord, field_pos: &[Range<usize>]) -> V
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
-    use csv::StringRecord;
+    use csv::ByteRecord;
 
     use super::parse_pos;
-    use crate::{extract_bytes, extract_chars, extract_fields};
+    use crate::{extract_bytes, extract_chars, extract_fields, run_with, Config, Extract};
+    use common::Format;
+    use std::io::{self, BufRead, Cursor};
 
     #[test]
     fn test_parse_pos() {
@@ -358,20 +623,97 @@ mod unit_tests {
 
     #[test]
     fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..1]), b"\xc3");
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..2]), "á".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..3]), "áb".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[0..4]), "ábc".as_bytes());
+        assert_eq!(extract_bytes("ábc".as_bytes(), &[3..4, 2..3]), b"cb");
+        assert_eq!(extract_bytes(b"caf\xe9", &[3..4]), b"\xe9");
     }
 
     #[test]
     fn test_eutract_fields() {
-        let rec = StringRecord::from(vec!["Captain", "Sham", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2]), &["Sham"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 2..3]), &["Captain", "12345"]);
-        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &["Captain"]);
-        assert_eq!(extract_fields(&rec, &[1..2, 0..1]), &["Sham", "Captain"]);
+        let rec = ByteRecord::from(vec!["Captain", "Sham", "12345"]);
+        assert_eq!(extract_fields(&rec, &[0..1]), &[b"Captain"]);
+        assert_eq!(extract_fields(&rec, &[1..2]), &[b"Sham"]);
+        assert_eq!(
+            extract_fields(&rec, &[0..1, 2..3]),
+            &[&b"Captain"[..], b"12345"]
+        );
+        assert_eq!(extract_fields(&rec, &[0..1, 3..4]), &[b"Captain"]);
+        assert_eq!(
+            extract_fields(&rec, &[1..2, 0..1]),
+            &[&b"Sham"[..], b"Captain"]
+        );
+    }
+
+    #[test]
+    fn test_run_with() {
+        let inputs = |name: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new(format!("{name},1,x\n{name},2,y\n"))))
+        };
+        let config = Config::builder()
+            .files(["a", "b"])
+            .delimiter(b',')
+            .extract(Extract::Fields(vec![0..1, 2..3]))
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"a,x\na,y\nb,x\nb,y\n");
+    }
+
+    #[test]
+    fn test_run_with_zero_terminated() {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new("a\tb\nc\0d\te\0")))
+        };
+        let config = Config::builder()
+            .extract(Extract::Fields(vec![1..2]))
+            .zero_terminated(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"b\nc\0e\0");
+
+        let config = Config::builder()
+            .extract(Extract::Bytes(vec![0..3]))
+            .zero_terminated(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"a\tb\0d\te\0");
+    }
+
+    #[test]
+    fn test_run_with_json() {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new("a\tb\tc\n\"d\"\t\te\n")))
+        };
+        let config = Config::builder()
+            .extract(Extract::Fields(vec![0..1, 2..3]))
+            .format(Format::Json)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n{\"file\":\"-\",\"record\":1,\"fields\":[\"a\",\"c\"]},\n\
+            {\"file\":\"-\",\"record\":2,\"fields\":[\"d\",\"e\"]}\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        assert!(Config::builder().build().is_err());
+        let config = Config::builder()
+            .extract(Extract::Bytes(vec![0..1]))
+            .build()
+            .unwrap();
+        assert_eq!(config.files, ["-"]);
+        assert_eq!(config.delimiter, b'\t');
     }
 }
@@ -1,6 +1,5 @@
 fn main() {
     if let Err(e) = cutr::get_args().and_then(cutr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+        e.exit();
     }
 }
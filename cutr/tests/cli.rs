@@ -7,8 +7,11 @@ type TestResult = Result<(), Box<dyn std::error::Error>>;
 
 const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
+const CONFIG: &str = "tests/config";
 const TSV: &str = "tests/inputs/movies1.tsv";
+const TSV_XZ: &str = "tests/inputs/movies1.tsv.xz";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const LATIN1: &str = "tests/inputs/latin1.tsv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -37,17 +40,114 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .args(["-f", "1", CSV, &bad, TSV])
         .assert()
-        .success()
+        .failure()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn closed_pipe() -> TestResult {
+    let mut args = vec!["-f", "1"];
+    args.extend([TSV; 5000]);
+    common::testing::run_closed_pipe(PRG, args)
+}
+
+#[test]
+fn config_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .arg(CSV)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("title\nThe Blues Brothers\n"));
+    Ok(())
+}
+
+#[test]
+fn config_file_conflicts_with_args() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .env("CUTR_OPTS", "-d ';'")
+        .args(["-b", "1-3", CSV])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("tit\nThe\n"));
+    Ok(())
+}
+
+#[test]
+fn tar_member_f1() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1", "tests/inputs/bundle.tar.gz//data/books.tsv"])
+        .assert()
+        .success()
+        .stdout("Author\nÉmile Zola\nSamuel Beckett\nJules Verne\n");
+    Ok(())
+}
+
+#[test]
+fn zip_member_f1() -> TestResult {
+    run(
+        &["-d", ",", "-f", "1", "tests/inputs/export.zip//report.csv"],
+        "tests/expected/movies1.csv.f1.dcomma.out",
+    )
+}
+
+#[test]
+fn skips_missing_member() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-f", "1", "tests/inputs/export.zip//nope.csv", CSV])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr("tests/inputs/export.zip//nope.csv: no such member in archive\n");
+    Ok(())
+}
+
+#[test]
+fn ndjson() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--ndjson", "-f", "1,3", BOOKS])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "{\"file\":\"tests/inputs/books.tsv\",\"record\":1,\"fields\":[\"Author\",\"Title\"]}\n\
+            {\"file\":\"tests/inputs/books.tsv\",\"record\":2,\"fields\":[\"Émile Zola\",\"La Confession de Claude\"]}\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-d", ",", "-f", "2"])
+        .write_stdin("a,b\nc\0d,e\0")
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"b\nc\0e\0"[..]));
+    Ok(())
+}
+
+#[test]
+fn generates_man_page() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "man"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH cutr"))
+        .stdout(predicate::str::contains("ranges of them such as 3\\-5"));
+    Ok(())
+}
+
 // --------------------------------------------------
 fn dies(args: &[&str], expected: &str) -> TestResult {
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .failure()
+        .code(2)
         .stderr(predicate::str::contains(expected));
     Ok(())
 }
@@ -159,14 +259,13 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
 }
 
 // --------------------------------------------------
-fn run_lossy(args: &[&str], expected_file: &str) -> TestResult {
-    let contents = fs::read(expected_file)?;
-    let expected = String::from_utf8_lossy(&contents);
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::str::contains(expected));
+        .stdout(expected);
     Ok(())
 }
 
@@ -275,7 +374,7 @@ fn tsv_b2() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn tsv_b8() -> TestResult {
-    run_lossy(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
+    run_bytes(&[TSV, "-b", "8"], "tests/expected/movies1.tsv.b8.out")
 }
 
 // --------------------------------------------------
@@ -293,7 +392,7 @@ fn tsv_b2_3() -> TestResult {
 // --------------------------------------------------
 #[test]
 fn tsv_b1_8() -> TestResult {
-    run_lossy(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
+    run_bytes(&[TSV, "-b", "1-8"], "tests/expected/movies1.tsv.b1-8.out")
 }
 
 // --------------------------------------------------
@@ -337,3 +436,21 @@ fn tsv_c1_8() -> TestResult {
 fn repeated_value() -> TestResult {
     run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn tsv_xz_f2() -> TestResult {
+    run(&[TSV_XZ, "-f", "2"], "tests/expected/movies1.tsv.f2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_f2() -> TestResult {
+    run_bytes(&[LATIN1, "-f", "2"], "tests/expected/latin1.tsv.f2.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn latin1_b4() -> TestResult {
+    run_bytes(&[LATIN1, "-b", "4"], "tests/expected/latin1.tsv.b4.out")
+}
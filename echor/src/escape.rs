@@ -0,0 +1,142 @@
+use std::ops::ControlFlow;
+
+/// Where backslash escapes are interpreted, which decides how octal escapes
+/// are read.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Dialect {
+    /// `echo -e` text and printf `%b` arguments, where `\0NNN` takes up to
+    /// three digits after the 0.
+    Echo,
+    /// A printf format, where `\NNN` takes up to three digits in all and
+    /// `\"` is a double quote.
+    Format,
+}
+
+/// What a backslash sequence stands for.
+pub enum Escape {
+    Byte(u8),
+    Char(char),
+    /// `\c`: nothing more is written.
+    Stop,
+}
+
+impl Escape {
+    /// Appends the escaped text to `out`, or breaks at `\c`.
+    pub fn write(self, out: &mut Vec<u8>) -> ControlFlow<()> {
+        match self {
+            Self::Byte(b) => out.push(b),
+            Self::Char(c) => out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            Self::Stop => return ControlFlow::Break(()),
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Appends `text` to `out` with backslash escapes replaced, as `echo -e`
+/// does:
+///
+/// - `\\`, `\a`, `\b`, `\e`, `\f`, `\n`, `\r`, `\t` and `\v`
+/// - `\0NNN` and `\NNN`, the byte with octal value NNN (up to 3 digits)
+/// - `\xHH`, the byte with hex value HH (1 or 2 digits)
+/// - `\uHHHH`, the character with hex value HHHH (1 to 4 digits)
+/// - `\c`, which ends the output
+///
+/// Anything else after a backslash, including nothing, is written as is.
+/// Returns [`ControlFlow::Break`] at `\c`.
+pub fn unescape(text: &str, out: &mut Vec<u8>) -> ControlFlow<()> {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = match bytes[i] {
+            b'\\' => parse(&bytes[i + 1..], Dialect::Echo),
+            _ => None,
+        };
+        match escape {
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+            Some((escape, len)) => {
+                escape.write(out)?;
+                i += 1 + len;
+            }
+        }
+    }
+    ControlFlow::Continue(())
+}
+
+/// Parses the escape following a backslash, returning it and its length,
+/// or None if the backslash is not the start of an escape.
+pub fn parse(rest: &[u8], dialect: Dialect) -> Option<(Escape, usize)> {
+    let byte = |b| Some((Escape::Byte(b), 1));
+    match *rest.first()? {
+        b'\\' => byte(b'\\'),
+        b'a' => byte(0x07),
+        b'b' => byte(0x08),
+        b'e' => byte(0x1b),
+        b'f' => byte(0x0c),
+        b'n' => byte(b'\n'),
+        b'r' => byte(b'\r'),
+        b't' => byte(b'\t'),
+        b'v' => byte(0x0b),
+        b'c' => Some((Escape::Stop, 1)),
+        b'"' if dialect == Dialect::Format => byte(b'"'),
+        b'0' if dialect == Dialect::Echo => {
+            let (value, len) = number(&rest[1..], 8, 3);
+            Some((Escape::Byte(value as u8), 1 + len))
+        }
+        b'0'..=b'7' => {
+            let (value, len) = number(rest, 8, 3);
+            Some((Escape::Byte(value as u8), len))
+        }
+        b'x' => match number(&rest[1..], 16, 2) {
+            (_, 0) => None,
+            (value, len) => Some((Escape::Byte(value as u8), 1 + len)),
+        },
+        b'u' => match number(&rest[1..], 16, 4) {
+            (_, 0) => None,
+            (value, len) => char::from_u32(value).map(|c| (Escape::Char(c), 1 + len)),
+        },
+        _ => None,
+    }
+}
+
+/// Reads up to `max` leading digits in `radix`, returning their value and
+/// how many there were.
+fn number(digits: &[u8], radix: u32, max: usize) -> (u32, usize) {
+    digits
+        .iter()
+        .take(max)
+        .map_while(|&b| char::from(b).to_digit(radix))
+        .fold((0, 0), |(value, len), digit| {
+            (value * radix + digit, len + 1)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unescape;
+    use std::ops::ControlFlow;
+
+    fn run(text: &str) -> (Vec<u8>, bool) {
+        let mut out = Vec::new();
+        let stopped = unescape(text, &mut out).is_break();
+        (out, stopped)
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(run("a\\tb\\\\c\\n"), (b"a\tb\\c\n".to_vec(), false));
+        assert_eq!(run("\\a\\b\\e\\f\\r\\v").0, b"\x07\x08\x1b\x0c\r\x0b");
+        assert_eq!(run("\\0101\\01017\\101\\0").0, b"AA7A\0");
+        assert_eq!(run("\\0777").0, b"\xff");
+        assert_eq!(run("\\x41\\x4\\x4aZ").0, b"A\x04JZ");
+        assert_eq!(run("\\u00e9\\u263a!").0, "é☺!".as_bytes());
+        assert_eq!(run("\\x \\u \\ud800 \\z \\").0, b"\\x \\u \\ud800 \\z \\");
+        assert_eq!(run("a\\cb"), (b"a".to_vec(), true));
+
+        let mut out = Vec::new();
+        assert_eq!(unescape("no escapes", &mut out), ControlFlow::Continue(()));
+        assert_eq!(out, b"no escapes");
+    }
+}
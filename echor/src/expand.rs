@@ -0,0 +1,146 @@
+/// Returns `text` with `${VAR}`, `${VAR:-default}` and `${VAR:?message}`
+/// replaced using `env`, and `$$` replaced by `$`. A default or message may
+/// itself contain expansions. Any other `$` is left as it is.
+///
+/// An error names the variable when `${VAR:?message}` finds it unset or
+/// empty, or quotes the expansion when it is malformed.
+pub fn expand(text: &[u8], env: &dyn Fn(&str) -> Option<Vec<u8>>) -> Result<Vec<u8>, String> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < text.len() {
+        match &text[i..] {
+            [b'$', b'$', ..] => {
+                out.push(b'$');
+                i += 2;
+            }
+            [b'$', b'{', rest @ ..] => {
+                let len = closing_brace(rest).ok_or_else(|| {
+                    format!("'{}': missing }}", String::from_utf8_lossy(&text[i..]))
+                })?;
+                out.extend(substitute(&rest[..len], env)?);
+                i += 2 + len + 1;
+            }
+            _ => {
+                out.push(text[i]);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The position of the `}` ending the expansion `rest` is the inside of,
+/// allowing for expansions nested in it.
+fn closing_brace(rest: &[u8]) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        match &rest[i..] {
+            [b'$', b'$', ..] => i += 1,
+            [b'$', b'{', ..] => {
+                depth += 1;
+                i += 1;
+            }
+            [b'}', ..] if depth == 0 => return Some(i),
+            [b'}', ..] => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Expands the inside of `${...}`.
+fn substitute(inside: &[u8], env: &dyn Fn(&str) -> Option<Vec<u8>>) -> Result<Vec<u8>, String> {
+    let bad = || {
+        format!(
+            "'${{{}}}': bad substitution",
+            String::from_utf8_lossy(inside)
+        )
+    };
+    let len = inside
+        .iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+        .count();
+    if len == 0 || inside[0].is_ascii_digit() {
+        return Err(bad());
+    }
+    // The name is ASCII, so this cannot fail.
+    let name = std::str::from_utf8(&inside[..len]).unwrap_or_default();
+    let value = env(name).filter(|value| !value.is_empty());
+    match &inside[len..] {
+        [] => Ok(value.unwrap_or_default()),
+        [b':', b'-', word @ ..] => match value {
+            Some(value) => Ok(value),
+            None => expand(word, env),
+        },
+        [b':', b'?', word @ ..] => match value {
+            Some(value) => Ok(value),
+            None if word.is_empty() => Err(format!("{name}: parameter null or not set")),
+            None => Err(format!(
+                "{name}: {}",
+                String::from_utf8_lossy(&expand(word, env)?)
+            )),
+        },
+        _ => Err(bad()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand;
+
+    fn env(name: &str) -> Option<Vec<u8>> {
+        match name {
+            "USER" => Some(b"ann".to_vec()),
+            "EMPTY" => Some(vec![]),
+            "HOST" => Some(b"db1".to_vec()),
+            _ => None,
+        }
+    }
+
+    fn run(text: &str) -> Result<String, String> {
+        expand(text.as_bytes(), &env).map(|out| String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(run("hi ${USER}!"), Ok("hi ann!".to_string()));
+        assert_eq!(run("[${NOPE}] [${EMPTY}]"), Ok("[] []".to_string()));
+        assert_eq!(
+            run("${USER:-x} ${NOPE:-x y} ${EMPTY:-}"),
+            Ok("ann x y ".to_string())
+        );
+        assert_eq!(
+            run("${NOPE:-${HOST}:${PORT:-5432}}"),
+            Ok("db1:5432".to_string())
+        );
+        assert_eq!(run("${HOST:?no host}"), Ok("db1".to_string()));
+        assert_eq!(
+            run("$USER $$ $${USER} ${ $"),
+            Err("'${ $': missing }".to_string())
+        );
+        assert_eq!(
+            run("$USER $$ $${USER} $"),
+            Ok("$USER $ ${USER} $".to_string())
+        );
+        assert_eq!(run("{${USER}}"), Ok("{ann}".to_string()));
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(run("${NOPE:?set NOPE}"), Err("NOPE: set NOPE".to_string()));
+        assert_eq!(
+            run("${EMPTY:?}"),
+            Err("EMPTY: parameter null or not set".to_string())
+        );
+        assert_eq!(
+            run("${NOPE:?${USER} needs it}"),
+            Err("NOPE: ann needs it".to_string())
+        );
+        assert_eq!(run("${USER"), Err("'${USER': missing }".to_string()));
+        assert_eq!(run("${}"), Err("'${}': bad substitution".to_string()));
+        assert_eq!(run("${1A}"), Err("'${1A}': bad substitution".to_string()));
+        assert_eq!(run("${A-b}"), Err("'${A-b}': bad substitution".to_string()));
+    }
+}
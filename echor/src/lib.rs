@@ -0,0 +1,649 @@
+mod escape;
+mod expand;
+mod printf;
+mod quote;
+mod repeat;
+mod style;
+
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal, Read, Write},
+    ops::ControlFlow,
+};
+
+use clap::{Arg, ArgAction, ArgGroup, ColorChoice, Command};
+use common::{Compat, Error, Failures, Result};
+use style::Style;
+
+pub use quote::Quote;
+pub use repeat::Repeat;
+pub use style::Color;
+
+#[derive(Debug)]
+pub struct Config {
+    text: Vec<String>,
+    omit_newline: bool,
+    escapes: bool,
+    format: Option<String>,
+    style: Style,
+    markup: bool,
+    color: bool,
+    expand: bool,
+    template: Option<String>,
+    quote: Option<Quote>,
+    separator: Option<String>,
+    repeat: Option<Repeat>,
+    rate: Option<f64>,
+    count_suffix: bool,
+    compat: Option<Compat>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    text: Vec<String>,
+    omit_newline: bool,
+    escapes: bool,
+    format: Option<String>,
+    style: Style,
+    markup: bool,
+    color: bool,
+    expand: bool,
+    template: Option<String>,
+    quote: Option<Quote>,
+    separator: Option<String>,
+    repeat: Option<Repeat>,
+    rate: Option<f64>,
+    count_suffix: bool,
+    compat: Option<Compat>,
+}
+
+impl ConfigBuilder {
+    /// Words to print, joined by single spaces.
+    pub fn text<I, S>(mut self, text: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.text = text.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn omit_newline(mut self, omit_newline: bool) -> Self {
+        self.omit_newline = omit_newline;
+        self
+    }
+
+    /// Interpret backslash escapes such as `\n` and `\c`, as `echo -e` does.
+    pub fn escapes(mut self, escapes: bool) -> Self {
+        self.escapes = escapes;
+        self
+    }
+
+    /// Format the text as arguments to this printf format instead of
+    /// joining it.
+    pub fn format(mut self, format: Option<String>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Write the text in this foreground color.
+    pub fn fg(mut self, fg: Option<Color>) -> Self {
+        self.style.fg = fg;
+        self
+    }
+
+    /// Write the text on this background color.
+    pub fn bg(mut self, bg: Option<Color>) -> Self {
+        self.style.bg = bg;
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.style.bold = bold;
+        self
+    }
+
+    pub fn underline(mut self, underline: bool) -> Self {
+        self.style.underline = underline;
+        self
+    }
+
+    /// Interpret tags such as `{red}` and `{/}` in the text.
+    pub fn markup(mut self, markup: bool) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    /// Write styles as ANSI escapes. Without this, styles are dropped but
+    /// markup tags are still removed.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Replace `${VAR}`, `${VAR:-default}` and `${VAR:?message}` in the
+    /// text, and the format, with values from the environment.
+    pub fn expand(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    /// Print this file, expanded as by [`expand`](Self::expand), instead of
+    /// the text.
+    pub fn template(mut self, template: Option<String>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Quote each word of the text.
+    pub fn quote(mut self, quote: Option<Quote>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Separate the words of the text with this instead of a space, or a
+    /// comma for CSV.
+    pub fn separator(mut self, separator: Option<String>) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Write the line this many times.
+    pub fn repeat(mut self, repeat: Option<Repeat>) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Write at most this many repeated lines a second.
+    pub fn rate(mut self, rate: Option<f64>) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Number repeated lines from 1, after the separator.
+    pub fn count_suffix(mut self, count_suffix: bool) -> Self {
+        self.count_suffix = count_suffix;
+        self
+    }
+
+    /// Interpret backslashes as another implementation does: `bsd` only
+    /// takes a `\c` ending the last word, which omits the newline, and
+    /// `posix` always interprets escapes. Neither can be combined with
+    /// [`escapes`](Self::escapes).
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.escapes && matches!(self.compat, Some(Compat::Bsd | Compat::Posix)) {
+            return Err(Error::Usage(
+                "-e and -E cannot be used with --compat bsd or posix".to_string(),
+            ));
+        }
+        if self.format.is_some() && (self.omit_newline || self.escapes || self.markup) {
+            return Err(Error::Usage(
+                "-n, -e and --markup cannot be used with --format".to_string(),
+            ));
+        }
+        if self.template.is_some() && (self.omit_newline || self.escapes || self.format.is_some()) {
+            return Err(Error::Usage(
+                "-n, -e and --format cannot be used with --template".to_string(),
+            ));
+        }
+        let quoting = self.quote.is_some() || self.separator.is_some();
+        if quoting && (self.format.is_some() || self.template.is_some()) {
+            return Err(Error::Usage(
+                "--quote and --separator cannot be used with --format or --template".to_string(),
+            ));
+        }
+        if self.quote.is_some() && self.markup {
+            return Err(Error::Usage(
+                "--quote cannot be used with --markup".to_string(),
+            ));
+        }
+        if self.quote == Some(Quote::Json) && self.separator.is_some() {
+            return Err(Error::Usage(
+                "--separator cannot be used with --quote=json".to_string(),
+            ));
+        }
+        if self.repeat.is_none() && (self.rate.is_some() || self.count_suffix) {
+            return Err(Error::Usage(
+                "--rate and --count-suffix need --repeat".to_string(),
+            ));
+        }
+        if self.repeat.is_some() && (self.format.is_some() || self.template.is_some()) {
+            return Err(Error::Usage(
+                "--repeat cannot be used with --format or --template".to_string(),
+            ));
+        }
+        Ok(Config {
+            text: self.text,
+            omit_newline: self.omit_newline,
+            escapes: self.escapes,
+            format: self.format,
+            style: self.style,
+            markup: self.markup,
+            color: self.color,
+            expand: self.expand,
+            template: self.template,
+            quote: self.quote,
+            separator: self.separator,
+            repeat: self.repeat,
+            rate: self.rate,
+            count_suffix: self.count_suffix,
+            compat: self.compat,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("echor")
+        .version("0.1.0")
+        .author("Talentaa <talentaa@qq.com>")
+        .about("Rust echo")
+        .arg(
+            Arg::new("text")
+                .value_name("TEXT")
+                .help("Input text")
+                .num_args(1..),
+        )
+        .arg(
+            Arg::new("omit_newline")
+                .short('n')
+                .action(ArgAction::SetTrue)
+                .help("Do not print newline"),
+        )
+        .arg(
+            Arg::new("escapes")
+                .short('e')
+                .action(ArgAction::SetTrue)
+                .overrides_with("no_escapes")
+                .help("Interpret backslash escapes"),
+        )
+        .arg(
+            Arg::new("no_escapes")
+                .short('E')
+                .action(ArgAction::SetTrue)
+                .overrides_with("escapes")
+                .help("Do not interpret backslash escapes (default)"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_names(["FORMAT", "ARG"])
+                .num_args(1..)
+                .allow_hyphen_values(true)
+                .conflicts_with_all(["omit_newline", "escapes", "markup"])
+                .help("Print the ARGs formatted by a printf FORMAT"),
+        )
+        .arg(
+            Arg::new("fg")
+                .long("fg")
+                .value_name("COLOR")
+                .value_parser(clap::value_parser!(Color))
+                .help("Foreground color"),
+        )
+        .arg(
+            Arg::new("bg")
+                .long("bg")
+                .value_name("COLOR")
+                .value_parser(clap::value_parser!(Color))
+                .help("Background color"),
+        )
+        .arg(
+            Arg::new("bold")
+                .long("bold")
+                .action(ArgAction::SetTrue)
+                .help("Bold text"),
+        )
+        .arg(
+            Arg::new("underline")
+                .long("underline")
+                .action(ArgAction::SetTrue)
+                .help("Underlined text"),
+        )
+        .arg(
+            Arg::new("markup")
+                .long("markup")
+                .action(ArgAction::SetTrue)
+                .help("Interpret style tags such as {red}...{/}"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("WHEN")
+                .value_parser(clap::value_parser!(ColorChoice))
+                .default_value("auto")
+                .help("When to write styles"),
+        )
+        .arg(
+            Arg::new("expand")
+                .long("expand")
+                .action(ArgAction::SetTrue)
+                .help("Expand ${VAR}, ${VAR:-default} and ${VAR:?message}"),
+        )
+        .arg(
+            Arg::new("template")
+                .long("template")
+                .value_name("FILE")
+                .conflicts_with_all(["omit_newline", "escapes"])
+                .help("Print FILE with its variables expanded"),
+        )
+        .arg(
+            Arg::new("quote")
+                .long("quote")
+                .value_name("STYLE")
+                .value_parser(clap::value_parser!(Quote))
+                .conflicts_with_all(["format", "template", "markup"])
+                .help("Quote each word of the text"),
+        )
+        .arg(
+            Arg::new("separator")
+                .long("separator")
+                .value_name("SEP")
+                .conflicts_with_all(["format", "template"])
+                .help("Separate words with SEP [default: space, or comma for CSV]"),
+        )
+        .arg(
+            Arg::new("repeat")
+                .long("repeat")
+                .value_name("N")
+                .value_parser(Repeat::parse)
+                .conflicts_with_all(["format", "template"])
+                .help("Write the line N times, or forever if N is inf"),
+        )
+        .arg(
+            Arg::new("rate")
+                .long("rate")
+                .value_name("LINES/SEC")
+                .value_parser(repeat::parse_rate)
+                .requires("repeat")
+                .help("Write at most LINES/SEC repeated lines a second"),
+        )
+        .arg(
+            Arg::new("count_suffix")
+                .long("count-suffix")
+                .action(ArgAction::SetTrue)
+                .requires("repeat")
+                .help("Number repeated lines from 1"),
+        )
+        .arg(Compat::arg())
+        .group(
+            ArgGroup::new("input")
+                .args(["text", "format", "template"])
+                .required(true),
+        )
+        .after_help(
+            "With -e, these escapes are interpreted:\n  \
+             \\\\      backslash\n  \
+             \\a      alert (BEL)\n  \
+             \\b      backspace\n  \
+             \\c      produce no further output\n  \
+             \\e      escape\n  \
+             \\f      form feed\n  \
+             \\n      new line\n  \
+             \\r      carriage return\n  \
+             \\t      horizontal tab\n  \
+             \\v      vertical tab\n  \
+             \\0NNN   byte with octal value NNN (1 to 3 digits)\n  \
+             \\xHH    byte with hexadecimal value HH (1 to 2 digits)\n  \
+             \\uHHHH  Unicode character with hexadecimal value HHHH (1 to 4 digits)\n\n\
+             Every argument after --format FORMAT is an ARG, even one starting \
+             with -. FORMAT takes the conversions %s %d %i %u %x %X %o %f %e %E %g %G \
+             %c %b and %%, with the flags -+ #0, a width and a precision, either of \
+             which may be * to take it from the next argument. %b formats its \
+             argument with the escapes above. The format is reused while \
+             arguments remain; its own escapes include \\NNN for octal.\n\n\
+             With --markup, {STYLE}...{/} writes the text between in STYLE: \
+             one or more of bold, underline, a COLOR and bg:COLOR, separated \
+             by commas, as in {bold,red}. Write {{ for a literal {. Styles are \
+             written when stdout is a terminal and NO_COLOR is unset, unless \
+             --color says otherwise.\n\n\
+             With --expand or --template, ${VAR} is the value of VAR, or \
+             nothing if it is unset; ${VAR:-default} is default if VAR is unset \
+             or empty; ${VAR:?message} fails with message if VAR is unset or \
+             empty; and $$ is a literal $. Other uses of $ are left as they \
+             are.\n\n\
+             With --quote=json, a single TEXT is written as a JSON string and \
+             several as an array. With -e, the escapes in TEXT and SEP are \
+             interpreted before quoting.",
+        )
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+    let compat = Compat::from_matches(&matches);
+    if matches.get_flag("no_escapes") && matches!(compat, Some(Compat::Bsd | Compat::Posix)) {
+        return Err(Error::Usage(
+            "-e and -E cannot be used with --compat bsd or posix".to_string(),
+        ));
+    }
+    // --format takes the format and then its arguments.
+    let (format, text) = match matches.get_many::<String>("format") {
+        Some(mut values) => (values.next().cloned(), values),
+        None => (None, matches.get_many("text").unwrap_or_default()),
+    };
+
+    Config::builder()
+        .text(text.cloned())
+        .omit_newline(matches.get_flag("omit_newline"))
+        .escapes(matches.get_flag("escapes"))
+        .format(format)
+        .fg(matches.get_one("fg").copied())
+        .bg(matches.get_one("bg").copied())
+        .bold(matches.get_flag("bold"))
+        .underline(matches.get_flag("underline"))
+        .markup(matches.get_flag("markup"))
+        .expand(matches.get_flag("expand"))
+        .template(matches.get_one("template").cloned())
+        .quote(matches.get_one("quote").copied())
+        .separator(matches.get_one("separator").cloned())
+        .repeat(matches.get_one("repeat").copied())
+        .rate(matches.get_one("rate").copied())
+        .count_suffix(matches.get_flag("count_suffix"))
+        .compat(compat)
+        .color(color_enabled(
+            *matches.get_one::<ColorChoice>("color").unwrap(),
+        ))
+        .build()
+}
+
+/// Whether `--color` allows styles: with `auto`, only when stdout is a
+/// terminal and `NO_COLOR` is unset or empty.
+fn color_enabled(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    }
+}
+
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::stdout())
+}
+
+/// Writes `config`'s text to `out`.
+pub fn run_with(config: &Config, mut out: impl Write) -> Result<()> {
+    let render = |text: &[u8]| {
+        let mut styled = Vec::new();
+        style::render(text, config.style, config.markup, config.color, &mut styled);
+        styled
+    };
+    // Styles end before a final newline.
+    let mut write_styled = |text: &[u8]| -> Result<()> {
+        let body = text.strip_suffix(b"\n").unwrap_or(text);
+        out.write_all(&render(body))?;
+        out.write_all(&text[body.len()..])?;
+        out.flush()?;
+        Ok(())
+    };
+    let lookup = |name: &str| env::var_os(name).map(OsString::into_encoded_bytes);
+    let mut failures = Failures::default();
+
+    if let Some(path) = &config.template {
+        let mut text = Vec::new();
+        common::open(path)
+            .map_err(|e| Error::open(path, e))?
+            .read_to_end(&mut text)
+            .map_err(|e| Error::read(path, e))?;
+        return match expand::expand(&text, &lookup) {
+            Ok(text) => write_styled(&text),
+            Err(msg) => {
+                failures.fail(&msg);
+                failures.finish()
+            }
+        };
+    }
+
+    let expanded = |word: &String| {
+        if !config.expand {
+            return Ok(word.clone());
+        }
+        expand::expand(word.as_bytes(), &lookup)
+            .map(|word| String::from_utf8_lossy(&word).into_owned())
+    };
+    let words = match config
+        .text
+        .iter()
+        .map(expanded)
+        .collect::<std::result::Result<Vec<_>, _>>()
+    {
+        Ok(words) => words,
+        // An expansion error leaves nothing to print.
+        Err(msg) => {
+            failures.fail(&msg);
+            return failures.finish();
+        }
+    };
+
+    if let Some(format) = &config.format {
+        let format = match expanded(format) {
+            Ok(format) => format,
+            Err(msg) => {
+                failures.fail(&msg);
+                return failures.finish();
+            }
+        };
+        let mut styled = style::Styled::new(&mut out, config.style, config.color);
+        printf::printf(&format, &words, &mut failures, &mut styled)?;
+        styled.finish()?;
+        return failures.finish();
+    }
+
+    let escapes = match config.compat {
+        Some(Compat::Bsd) => false,
+        Some(Compat::Posix) => true,
+        _ => config.escapes,
+    };
+    let unescaped = |word: &str, out: &mut Vec<u8>| {
+        if escapes {
+            escape::unescape(word, out)
+        } else {
+            out.extend_from_slice(word.as_bytes());
+            ControlFlow::Continue(())
+        }
+    };
+    let mut parts = vec![];
+    let mut stopped = false;
+    for word in &words {
+        let mut part = Vec::new();
+        stopped = unescaped(word, &mut part).is_break();
+        parts.push(part);
+        if stopped {
+            break;
+        }
+    }
+    // BSD echo only takes a \c ending the last word, to omit the newline.
+    if config.compat == Some(Compat::Bsd) {
+        if let Some(last) = parts.last_mut().filter(|last| last.ends_with(b"\\c")) {
+            last.truncate(last.len() - 2);
+            stopped = true;
+        }
+    }
+    let separator = match &config.separator {
+        Some(separator) => {
+            let mut bytes = Vec::new();
+            // A \c in the separator has nothing left to stop.
+            let _ = unescaped(separator, &mut bytes);
+            bytes
+        }
+        None => config
+            .quote
+            .map_or(&b" "[..], Quote::default_separator)
+            .to_vec(),
+    };
+
+    let mut text = Vec::new();
+    match config.quote {
+        Some(quote) => quote.write(&parts, &separator, &mut text),
+        None => text = parts.join(separator.as_slice()),
+    }
+    let ending: &[u8] = if config.omit_newline || stopped {
+        b""
+    } else {
+        b"\n"
+    };
+    let Some(times) = config.repeat else {
+        text.extend_from_slice(ending);
+        return write_styled(&text);
+    };
+    let text = render(&text);
+    let line = repeat::Line {
+        text: &text,
+        ending,
+        count_separator: config.count_suffix.then_some(separator.as_slice()),
+    };
+    repeat::repeat(&line, times, config.rate, &mut out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config};
+
+    #[test]
+    fn test_run_with() {
+        let config = Config::builder().text(["Hello", "there"]).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        assert_eq!(out, b"Hello there\n");
+
+        let config = Config::builder()
+            .text(["Hello"])
+            .omit_newline(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        assert_eq!(out, b"Hello");
+
+        let config = Config::builder()
+            .text(["a\\tb", "c\\cd", "e"])
+            .escapes(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        assert_eq!(out, b"a\tb c");
+    }
+}
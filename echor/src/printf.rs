@@ -0,0 +1,735 @@
+use std::{
+    io::{self, Write},
+    ops::ControlFlow,
+};
+
+use common::{Failures, Result};
+
+use crate::escape::{self, Dialect};
+
+/// A part of a format.
+#[derive(Debug, PartialEq)]
+enum Piece {
+    Text(Vec<u8>),
+    /// `\c`, which ends the output.
+    Stop,
+    Spec(Spec),
+    /// An invalid specification, which is reported as a failure and ends
+    /// the output once what comes before it has been written.
+    Invalid(String),
+}
+
+/// A conversion specification such as `%-8.3s`.
+#[derive(Debug, Default, PartialEq)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: Option<Count>,
+    precision: Option<Count>,
+    conversion: u8,
+}
+
+/// A width or precision, given in the format or taken from an argument by
+/// `*`.
+#[derive(Debug, PartialEq)]
+enum Count {
+    Fixed(usize),
+    Arg,
+}
+
+/// Writes `args` to `out` formatted by `format`, as printf(1) does. The
+/// format is reused while arguments remain, and missing arguments are
+/// taken as empty strings or zero.
+///
+/// Arguments that are not valid numbers are reported to `failures` and as
+/// much of them as could be read is used. An invalid specification, or a
+/// `*` width or precision beyond the range of an `int`, is reported to
+/// `failures` too, and ends the output.
+pub fn printf(
+    format: &str,
+    args: &[String],
+    failures: &mut Failures,
+    out: &mut impl Write,
+) -> Result<()> {
+    let pieces = parse_format(format);
+    let mut args = Args {
+        args,
+        next: 0,
+        failures,
+    };
+    loop {
+        let start = args.next;
+        for piece in &pieces {
+            let flow = match piece {
+                Piece::Text(text) => {
+                    out.write_all(text)?;
+                    ControlFlow::Continue(())
+                }
+                Piece::Stop => ControlFlow::Break(()),
+                Piece::Spec(spec) => spec.write(&mut args, out)?,
+                Piece::Invalid(msg) => {
+                    args.failures.fail(msg);
+                    ControlFlow::Break(())
+                }
+            };
+            if flow.is_break() {
+                return Ok(());
+            }
+        }
+        if args.next == start {
+            if let Some(arg) = args.args.get(start) {
+                Failures::warn(&format!("ignoring excess arguments, starting with '{arg}'"));
+            }
+            return Ok(());
+        }
+        if args.next >= args.args.len() {
+            return Ok(());
+        }
+    }
+}
+
+fn parse_format(format: &str) -> Vec<Piece> {
+    let bytes = format.as_bytes();
+    let mut pieces = vec![];
+    let mut text = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => match escape::parse(&bytes[i + 1..], Dialect::Format) {
+                None => {
+                    text.push(b'\\');
+                    i += 1;
+                }
+                Some((escape, len)) => {
+                    if escape.write(&mut text).is_break() {
+                        pieces.push(Piece::Text(text));
+                        pieces.push(Piece::Stop);
+                        return pieces;
+                    }
+                    i += 1 + len;
+                }
+            },
+            b'%' if bytes.get(i + 1) == Some(&b'%') => {
+                text.push(b'%');
+                i += 2;
+            }
+            b'%' => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                match parse_spec(&bytes[i..]) {
+                    Ok((spec, len)) => {
+                        pieces.push(Piece::Spec(spec));
+                        i += len;
+                    }
+                    Err(msg) => {
+                        pieces.push(Piece::Invalid(msg));
+                        return pieces;
+                    }
+                }
+            }
+            b => {
+                text.push(b);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+/// Parses the specification at the start of `bytes`, which begins with
+/// `%`, returning it and its length, or the message for an invalid one.
+fn parse_spec(bytes: &[u8]) -> std::result::Result<(Spec, usize), String> {
+    let mut spec = Spec::default();
+    let mut i = 1;
+    while let Some(&flag) = bytes.get(i) {
+        match flag {
+            b'-' => spec.left = true,
+            b'+' => spec.plus = true,
+            b' ' => spec.space = true,
+            b'#' => spec.alternate = true,
+            b'0' => spec.zero = true,
+            // Digit grouping, which has no effect in the C locale.
+            b'\'' => {}
+            _ => break,
+        }
+        i += 1;
+    }
+
+    let invalid = |end: usize| {
+        let spec = String::from_utf8_lossy(&bytes[..end.min(bytes.len())]);
+        format!("{spec}: invalid conversion specification")
+    };
+    let count = |i: &mut usize| -> std::result::Result<Option<Count>, String> {
+        if bytes.get(*i) == Some(&b'*') {
+            *i += 1;
+            return Ok(Some(Count::Arg));
+        }
+        let digits = bytes[*i..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return Ok(None);
+        }
+        let value = std::str::from_utf8(&bytes[*i..*i + digits])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .filter(|&value| value <= i32::MAX as usize)
+            .ok_or_else(|| invalid(*i + digits))?;
+        *i += digits;
+        Ok(Some(Count::Fixed(value)))
+    };
+    spec.width = count(&mut i)?;
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        spec.precision = Some(count(&mut i)?.unwrap_or(Count::Fixed(0)));
+    }
+    // Length modifiers make no difference here, but %b takes none.
+    let modifiers = i;
+    while matches!(
+        bytes.get(i),
+        Some(b'h' | b'l' | b'L' | b'q' | b'j' | b'z' | b't')
+    ) {
+        i += 1;
+    }
+
+    spec.conversion = *bytes.get(i).ok_or_else(|| invalid(i))?;
+    let valid = match spec.conversion {
+        b'd' | b'i' | b'u' => !spec.alternate,
+        b'o' | b'x' | b'X' | b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => true,
+        b's' => !spec.alternate && !spec.zero,
+        b'b' => !spec.alternate && !spec.zero && spec.precision.is_none() && i == modifiers,
+        b'c' => !spec.alternate && !spec.zero && spec.precision.is_none(),
+        _ => false,
+    };
+    if !valid {
+        return Err(invalid(i + 1));
+    }
+    Ok((spec, i + 1))
+}
+
+/// The arguments still to be formatted.
+struct Args<'a> {
+    args: &'a [String],
+    next: usize,
+    failures: &'a mut Failures,
+}
+
+impl<'a> Args<'a> {
+    fn next(&mut self) -> &'a str {
+        let arg = self.args.get(self.next).map_or("", String::as_str);
+        self.next = (self.next + 1).min(self.args.len());
+        arg
+    }
+
+    fn report(&mut self, arg: &str, msg: Option<&str>) {
+        if let Some(msg) = msg {
+            self.failures.fail(&format!("'{arg}': {msg}"));
+        }
+    }
+
+    fn integer(&mut self) -> i128 {
+        let arg = self.next();
+        let (value, msg) = parse_integer(arg);
+        self.report(arg, msg);
+        value
+    }
+
+    /// Reads a `*` width or precision, reporting `name` as invalid if it
+    /// does not fit in an `int`.
+    fn count(&mut self, name: &str) -> Option<i32> {
+        let arg = self.next();
+        let (value, msg) = parse_integer(arg);
+        self.report(arg, msg);
+        let count = i32::try_from(value).ok();
+        if count.is_none() {
+            self.failures.fail(&format!("invalid {name}: '{arg}'"));
+        }
+        count
+    }
+
+    fn float(&mut self) -> f64 {
+        let arg = self.next();
+        let (value, msg) = parse_float(arg);
+        self.report(arg, msg);
+        value
+    }
+}
+
+const OUT_OF_RANGE: &str = "Numerical result out of range";
+const NOT_NUMERIC: &str = "expected a numeric value";
+const NOT_CONVERTED: &str = "value not completely converted";
+
+/// The character code of an argument such as `'a` or `"a`.
+fn char_constant(arg: &str) -> Option<u32> {
+    let mut chars = arg.chars();
+    matches!(chars.next(), Some('\'' | '"')).then(|| chars.next().map_or(0, u32::from))
+}
+
+/// Reads an integer as strtoimax(3) does: after optional whitespace and a
+/// sign, a decimal, `0x` hexadecimal or `0` octal number. Returns the
+/// value, which saturates beyond 64 bits, and any problem with the
+/// argument.
+fn parse_integer(arg: &str) -> (i128, Option<&'static str>) {
+    if let Some(code) = char_constant(arg) {
+        return (code.into(), None);
+    }
+    if arg.is_empty() {
+        return (0, None);
+    }
+    let rest = arg.trim_start();
+    let (negative, rest) = match rest.as_bytes().first() {
+        Some(b'-') => (true, &rest[1..]),
+        Some(b'+') => (false, &rest[1..]),
+        _ => (false, rest),
+    };
+    let hex = (rest.starts_with("0x") || rest.starts_with("0X"))
+        && rest[2..].starts_with(|c: char| c.is_ascii_hexdigit());
+    let (radix, digits) = match rest.as_bytes() {
+        _ if hex => (16, &rest[2..]),
+        [b'0', ..] => (8, rest),
+        _ => (10, rest),
+    };
+
+    let len = digits.chars().take_while(|c| c.is_digit(radix)).count();
+    if len == 0 {
+        return (0, Some(NOT_NUMERIC));
+    }
+    let limit = i128::from(u64::MAX);
+    let mut value: i128 = 0;
+    let mut msg = (len < digits.len()).then_some(NOT_CONVERTED);
+    for c in digits[..len].chars() {
+        value = value * i128::from(radix) + i128::from(c.to_digit(radix).unwrap_or(0));
+        if value > limit {
+            value = limit;
+            msg = Some(OUT_OF_RANGE);
+        }
+    }
+    (if negative { -value } else { value }, msg)
+}
+
+/// Reads a floating-point number, such as `1.5`, `-2e10` or `inf`.
+fn parse_float(arg: &str) -> (f64, Option<&'static str>) {
+    if let Some(code) = char_constant(arg) {
+        return (code.into(), None);
+    }
+    if arg.is_empty() {
+        return (0.0, None);
+    }
+    let rest = arg.trim_start();
+    (1..=rest.len())
+        .rev()
+        .filter(|&end| rest.is_char_boundary(end))
+        .find_map(|end| rest[..end].parse().ok().map(|value| (value, end)))
+        .map_or((0.0, Some(NOT_NUMERIC)), |(value, end)| {
+            (value, (end < rest.len()).then_some(NOT_CONVERTED))
+        })
+}
+
+impl Spec {
+    /// Formats the next argument, breaking when a `%b` argument holds `\c`
+    /// or a `*` width or precision is invalid.
+    fn write(&self, args: &mut Args, out: &mut dyn Write) -> Result<ControlFlow<()>> {
+        let mut left = self.left;
+        let width = match self.width {
+            None => 0,
+            Some(Count::Fixed(width)) => width,
+            Some(Count::Arg) => {
+                let Some(width) = args.count("field width") else {
+                    return Ok(ControlFlow::Break(()));
+                };
+                left |= width < 0;
+                width.unsigned_abs() as usize
+            }
+        };
+        let precision = match self.precision {
+            None => None,
+            Some(Count::Fixed(precision)) => Some(precision),
+            Some(Count::Arg) => match args.count("precision") {
+                // A negative precision is taken as none.
+                Some(precision) => usize::try_from(precision).ok(),
+                None => return Ok(ControlFlow::Break(())),
+            },
+        };
+        // `zeros` come between the prefix and the body, before any zero
+        // fill.
+        let pad = |out: &mut dyn Write, prefix: &str, zeros: usize, body: &[u8], zero: bool| {
+            let fill = width.saturating_sub(prefix.len() + zeros + body.len());
+            let (before, zeros, after) = if left {
+                (0, zeros, fill)
+            } else if zero {
+                (0, zeros + fill, 0)
+            } else {
+                (fill, zeros, 0)
+            };
+            repeat_byte(out, b' ', before)?;
+            out.write_all(prefix.as_bytes())?;
+            repeat_byte(out, b'0', zeros)?;
+            out.write_all(body)?;
+            repeat_byte(out, b' ', after)
+        };
+
+        match self.conversion {
+            b's' => {
+                let arg = args.next().as_bytes();
+                pad(
+                    out,
+                    "",
+                    0,
+                    &arg[..precision.unwrap_or(arg.len()).min(arg.len())],
+                    false,
+                )?;
+            }
+            b'c' => {
+                let arg = args.next().as_bytes();
+                pad(out, "", 0, &[arg.first().copied().unwrap_or(0)], false)?;
+            }
+            b'b' => {
+                let mut text = Vec::new();
+                let flow = escape::unescape(args.next(), &mut text);
+                pad(out, "", 0, &text, false)?;
+                return Ok(flow);
+            }
+            b'd' | b'i' => {
+                let value = args.integer();
+                let value = value.clamp(i64::MIN.into(), i64::MAX.into());
+                let sign = match value {
+                    ..=-1 => "-",
+                    _ if self.plus => "+",
+                    _ if self.space => " ",
+                    _ => "",
+                };
+                let (zeros, digits) = self.digits(value.unsigned_abs(), precision);
+                pad(
+                    out,
+                    sign,
+                    zeros,
+                    digits.as_bytes(),
+                    self.zero && precision.is_none(),
+                )?;
+            }
+            b'u' | b'o' | b'x' | b'X' => {
+                // Negative values wrap around, as with strtoumax(3).
+                let value = args.integer();
+                let value = if value < 0 {
+                    (1 << 64) + value.max(-i128::from(u64::MAX))
+                } else {
+                    value
+                };
+                let prefix = match self.conversion {
+                    b'x' if self.alternate && value != 0 => "0x",
+                    b'X' if self.alternate && value != 0 => "0X",
+                    _ => "",
+                };
+                let (zeros, digits) = self.digits(value.unsigned_abs(), precision);
+                pad(
+                    out,
+                    prefix,
+                    zeros,
+                    digits.as_bytes(),
+                    self.zero && precision.is_none(),
+                )?;
+            }
+            _ => {
+                let value = args.float();
+                let sign = match value.is_sign_negative() {
+                    true => "-",
+                    false if self.plus => "+",
+                    false if self.space => " ",
+                    false => "",
+                };
+                let body = self.float(value.abs(), precision.unwrap_or(6));
+                pad(
+                    out,
+                    sign,
+                    0,
+                    body.as_bytes(),
+                    self.zero && value.is_finite(),
+                )?;
+            }
+        }
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// The digits of an integer conversion, and how many zeros go before
+    /// them to make at least `precision` digits.
+    fn digits(&self, value: u128, precision: Option<usize>) -> (usize, String) {
+        let mut digits = match self.conversion {
+            b'o' => format!("{value:o}"),
+            b'x' => format!("{value:x}"),
+            b'X' => format!("{value:X}"),
+            _ => value.to_string(),
+        };
+        if precision == Some(0) && value == 0 {
+            digits.clear();
+        }
+        let mut zeros = precision.map_or(0, |precision| precision.saturating_sub(digits.len()));
+        if self.conversion == b'o' && self.alternate && zeros == 0 && !digits.starts_with('0') {
+            zeros = 1;
+        }
+        (zeros, digits)
+    }
+
+    /// Formats a non-negative `value` for `%f`, `%e` or `%g`.
+    fn float(&self, value: f64, precision: usize) -> String {
+        let body = if value.is_nan() {
+            "nan".to_string()
+        } else if value.is_infinite() {
+            "inf".to_string()
+        } else {
+            match self.conversion.to_ascii_lowercase() {
+                b'f' => fixed(value, precision, self.alternate),
+                b'e' => exponent(value, precision, self.alternate),
+                _ => general(value, precision, self.alternate),
+            }
+        };
+        if self.conversion.is_ascii_uppercase() {
+            body.to_uppercase()
+        } else {
+            body
+        }
+    }
+}
+
+/// Writes `len` copies of `byte` to `out` a block at a time, as a width or
+/// precision can run to gigabytes.
+fn repeat_byte(out: &mut dyn Write, byte: u8, mut len: usize) -> io::Result<()> {
+    let block = [byte; 8 * 1024];
+    while len > 0 {
+        let chunk = len.min(block.len());
+        out.write_all(&block[..chunk])?;
+        len -= chunk;
+    }
+    Ok(())
+}
+
+/// `%f`: `precision` digits after the point.
+fn fixed(value: f64, precision: usize, alternate: bool) -> String {
+    let point = if alternate && precision == 0 { "." } else { "" };
+    format!("{value:.precision$}{point}")
+}
+
+/// `%e`: one digit before the point, `precision` after, and an exponent of
+/// at least two digits.
+fn exponent(value: f64, precision: usize, alternate: bool) -> String {
+    let text = format!("{value:.precision$e}");
+    let (mantissa, exp) = text.split_once('e').unwrap_or((&text, "0"));
+    let exp: i32 = exp.parse().unwrap_or(0);
+    let point = if alternate && precision == 0 { "." } else { "" };
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{mantissa}{point}e{sign}{:02}", exp.unsigned_abs())
+}
+
+/// `%g`: `precision` significant digits, as `%e` for small and large
+/// exponents and as `%f` otherwise, without trailing zeros unless
+/// `alternate`.
+fn general(value: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let exp = if value == 0.0 {
+        0
+    } else {
+        let text = format!("{value:.*e}", precision - 1);
+        text.split_once('e')
+            .and_then(|(_, exp)| exp.parse().ok())
+            .unwrap_or(0)
+    };
+    let text = if (-4..precision as i32).contains(&exp) {
+        fixed(value, (precision as i32 - 1 - exp) as usize, alternate)
+    } else {
+        exponent(value, precision - 1, alternate)
+    };
+    if alternate {
+        return text;
+    }
+    let (mantissa, exp) = match text.find('e') {
+        Some(i) => text.split_at(i),
+        None => (text.as_str(), ""),
+    };
+    match mantissa.contains('.') {
+        true => format!(
+            "{}{exp}",
+            mantissa.trim_end_matches('0').trim_end_matches('.')
+        ),
+        false => text.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_float, parse_integer, parse_spec, printf};
+    use common::Failures;
+    use std::io::{self, Write};
+
+    fn run(format: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = Vec::new();
+        let mut failures = Failures::default();
+        printf(format, &args, &mut failures, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(run("%s-%s|", &["a", "b", "c"]), "a-b|c-|");
+        assert_eq!(
+            run("%5s|%-5s|%.2s|", &["ab", "cd", "efg"]),
+            "   ab|cd   |ef|"
+        );
+        assert_eq!(run("%c%c|%3c", &["hello", "", "x"]), "h\0|  x");
+        assert_eq!(run("%b|%s", &["\\0101\\t", "\\t"]), "A\t|\\t");
+        assert_eq!(run("a%%b\\n\\0101\\x41\\\"", &[]), "a%b\n\x081A\"");
+        assert_eq!(run("x\\cy%s", &["z"]), "x");
+        assert_eq!(run("%b %s", &["a\\cb", "z"]), "a");
+        assert_eq!(run("no conversions\\n", &["a", "b"]), "no conversions\n");
+    }
+
+    #[test]
+    fn test_integers() {
+        assert_eq!(run("%d %i %d|", &["42", "0x1F", "010"]), "42 31 8|");
+        assert_eq!(
+            run("%+d|% d|%05d|%-5d|", &["5", "5", "-42", "7"]),
+            "+5| 5|-0042|7    |"
+        );
+        assert_eq!(run("%.3d|%8.3d|%.0d|", &["5", "-5", "0"]), "005|    -005||");
+        assert_eq!(
+            run("%*d|%-*d|%.*d", &["5", "1", "-4", "2", "3", "7"]),
+            "    1|2   |007"
+        );
+        assert_eq!(
+            run("%u %x", &["-1", "-1"]),
+            "18446744073709551615 ffffffffffffffff"
+        );
+        assert_eq!(
+            run("%x %X %#x %#o %o", &["255", "255", "255", "8", "0"]),
+            "ff FF 0xff 010 0"
+        );
+        assert_eq!(run("%d %d", &["'a", "\"b"]), "97 98");
+        assert_eq!(run("%ld %d", &["5"]), "5 0");
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(
+            run("%f|%5.2f|%.0f|%#.0f", &["1.5", "3.14159", "2.5", "3"]),
+            "1.500000| 3.14|2|3."
+        );
+        assert_eq!(
+            run("%e|%.2E|%e", &["12345.678", "0", "-0.00012"]),
+            "1.234568e+04|0.00E+00|-1.200000e-04"
+        );
+        assert_eq!(
+            run(
+                "%g %g %g %g %g",
+                &["0.0001", "100000", "1000000", "0", "123456789"]
+            ),
+            "0.0001 100000 1e+06 0 1.23457e+08"
+        );
+        assert_eq!(
+            run("%#g|%#.3g|%G", &["1", "12", "1e-10"]),
+            "1.00000|12.0|1E-10"
+        );
+        assert_eq!(
+            run("%f %E %G|%5f", &["inf", "-inf", "nan", "inf"]),
+            "inf -INF NAN|  inf"
+        );
+        assert_eq!(run("%-+6.1f|%06.2f", &["2", "-1.5"]), "+2.0  |-01.50");
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_integer(" -12"), (-12, None));
+        assert_eq!(parse_integer(""), (0, None));
+        assert_eq!(parse_integer("abc"), (0, Some("expected a numeric value")));
+        assert_eq!(
+            parse_integer("12abc"),
+            (12, Some("value not completely converted"))
+        );
+        assert_eq!(
+            parse_integer("0x"),
+            (0, Some("value not completely converted"))
+        );
+        assert_eq!(
+            parse_integer("99999999999999999999"),
+            (u64::MAX.into(), Some("Numerical result out of range"))
+        );
+        assert_eq!(parse_float("1e3"), (1000.0, None));
+        assert_eq!(
+            parse_float("3.5x"),
+            (3.5, Some("value not completely converted"))
+        );
+        assert_eq!(parse_float("x"), (0.0, Some("expected a numeric value")));
+    }
+
+    /// Runs `format`, expecting it to be reported as a failure.
+    fn run_failing(format: &str, args: &[&str]) -> String {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = Vec::new();
+        let mut failures = Failures::default();
+        printf(format, &args, &mut failures, &mut out).unwrap();
+        assert!(failures.finish().is_err(), "{format}");
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Counts what is written without keeping it.
+    struct Counter(usize);
+
+    impl Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_padding() {
+        assert_eq!(run("%10000d|", &["1"]), format!("{}1|", " ".repeat(9999)));
+        assert_eq!(run("%-.10000d|", &["1"]), format!("{}1|", "0".repeat(9999)));
+        assert_eq!(run("%#.3o|%#o", &["8", "8"]), "010|010");
+
+        let long = [
+            ("%2147483647d", "1", i32::MAX as usize),
+            ("%.*d", "2147483647", i32::MAX as usize),
+            ("%*d", "-2147483648", 1 << 31),
+        ];
+        for (format, count, len) in long {
+            let mut counter = Counter(0);
+            let mut failures = Failures::default();
+            let args = [count.to_string()];
+            printf(format, &args, &mut failures, &mut counter).unwrap();
+            assert!(failures.finish().is_ok(), "{format}");
+            assert_eq!(counter.0, len, "{format}");
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for format in ["%", "%z", "%5%", "%05s", "%#d", "%.3c", "%.2b", "%lb"] {
+            assert_eq!(run_failing(format, &[]), "");
+        }
+        assert_eq!(
+            parse_spec(b"%ycd"),
+            Err("%y: invalid conversion specification".to_string())
+        );
+        assert_eq!(run_failing("ab%ycd", &[]), "ab");
+        assert_eq!(run_failing("<%s>%z", &["1", "2"]), "<1>");
+
+        assert_eq!(run_failing("%d,", &["x", "3"]), "0,3,");
+
+        // `*` counts must fit in an int, as fixed ones must.
+        assert_eq!(run_failing("%*d|", &["18446744073709551615", "1"]), "");
+        assert_eq!(run_failing("x%*d|", &["2147483648", "1"]), "x");
+        assert_eq!(run_failing("x%.*d|", &["3000000000", "1"]), "x");
+        assert_eq!(run_failing("x%.*d|", &["-2147483649", "1"]), "x");
+    }
+}
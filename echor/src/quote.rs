@@ -0,0 +1,178 @@
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// How `--quote` writes each word.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Quote {
+    /// A word for a POSIX shell, in single quotes when it needs them.
+    Shell,
+    /// A JSON string, or an array of them for several words.
+    Json,
+    /// A CSV field, in double quotes when it needs them.
+    Csv,
+    /// A C string literal.
+    C,
+}
+
+impl ValueEnum for Quote {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[Self::Shell, Self::Json, Self::Csv, Self::C]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            Self::Shell => PossibleValue::new("shell").help("Words for a POSIX shell"),
+            Self::Json => PossibleValue::new("json").help("A JSON string, or an array of them"),
+            Self::Csv => PossibleValue::new("csv").help("A CSV row"),
+            Self::C => PossibleValue::new("c").help("C string literals"),
+        })
+    }
+}
+
+impl Quote {
+    /// The separator used when none is given.
+    pub fn default_separator(self) -> &'static [u8] {
+        match self {
+            Self::Csv => b",",
+            _ => b" ",
+        }
+    }
+
+    /// Appends `words` to `out` quoted, with `separator` between them. JSON
+    /// arrays always separate their strings with commas.
+    pub fn write(self, words: &[Vec<u8>], separator: &[u8], out: &mut Vec<u8>) {
+        if self == Self::Json {
+            let words: Vec<_> = words
+                .iter()
+                .map(|word| String::from_utf8_lossy(word))
+                .collect();
+            let json = match words.as_slice() {
+                [word] => serde_json::to_vec(word),
+                words => serde_json::to_vec(words),
+            };
+            out.extend(json.expect("strings always serialize"));
+            return;
+        }
+        for (i, word) in words.iter().enumerate() {
+            if i > 0 {
+                out.extend_from_slice(separator);
+            }
+            match self {
+                Self::Shell => shell(word, out),
+                Self::Csv => csv(word, separator, out),
+                Self::C => c(word, out),
+                Self::Json => unreachable!(),
+            }
+        }
+    }
+}
+
+fn shell(word: &[u8], out: &mut Vec<u8>) {
+    let safe = |b: &u8| b.is_ascii_alphanumeric() || b"_@%+=:,./-".contains(b);
+    if !word.is_empty() && word.iter().all(safe) {
+        out.extend_from_slice(word);
+        return;
+    }
+    out.push(b'\'');
+    for &b in word {
+        match b {
+            b'\'' => out.extend_from_slice(b"'\\''"),
+            b => out.push(b),
+        }
+    }
+    out.push(b'\'');
+}
+
+fn csv(field: &[u8], separator: &[u8], out: &mut Vec<u8>) {
+    let special = |b: &u8| matches!(b, b'"' | b'\r' | b'\n');
+    let quoted = field.iter().any(special)
+        || (!separator.is_empty() && field.windows(separator.len()).any(|w| w == separator));
+    if !quoted {
+        out.extend_from_slice(field);
+        return;
+    }
+    out.push(b'"');
+    for &b in field {
+        if b == b'"' {
+            out.push(b'"');
+        }
+        out.push(b);
+    }
+    out.push(b'"');
+}
+
+/// Bytes other than printable ASCII are written as octal escapes, which
+/// unlike `\x` cannot run into a following digit.
+fn c(word: &[u8], out: &mut Vec<u8>) {
+    out.push(b'"');
+    for &b in word {
+        let escape: &[u8] = match b {
+            b'\\' => b"\\\\",
+            b'"' => b"\\\"",
+            b'\n' => b"\\n",
+            b'\t' => b"\\t",
+            b'\r' => b"\\r",
+            0x07 => b"\\a",
+            0x08 => b"\\b",
+            0x0b => b"\\v",
+            0x0c => b"\\f",
+            // Keeps "??" from forming a trigraph.
+            b'?' if out.last() == Some(&b'?') => b"\\?",
+            b' '..=b'~' => {
+                out.push(b);
+                continue;
+            }
+            b => {
+                out.extend(format!("\\{b:03o}").bytes());
+                continue;
+            }
+        };
+        out.extend_from_slice(escape);
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quote;
+
+    fn run(quote: Quote, words: &[&str]) -> String {
+        let words: Vec<_> = words.iter().map(|w| w.as_bytes().to_vec()).collect();
+        let mut out = Vec::new();
+        quote.write(&words, quote.default_separator(), &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_shell() {
+        assert_eq!(
+            run(Quote::Shell, &["a/b.c", "", "it's", "$HOME *"]),
+            r"a/b.c '' 'it'\''s' '$HOME *'"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(run(Quote::Json, &["say \"hi\"\n"]), r#""say \"hi\"\n""#);
+        assert_eq!(run(Quote::Json, &["a", "é\t"]), r#"["a","é\t"]"#);
+        assert_eq!(run(Quote::Json, &[]), "[]");
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(
+            run(Quote::Csv, &["a", "b,c", "say \"hi\"", "", "x\ny"]),
+            "a,\"b,c\",\"say \"\"hi\"\"\",,\"x\ny\""
+        );
+        let mut out = Vec::new();
+        Quote::Csv.write(&[b"a,b".to_vec(), b"c\td".to_vec()], b"\t", &mut out);
+        assert_eq!(out, b"a,b\t\"c\td\"");
+    }
+
+    #[test]
+    fn test_c() {
+        assert_eq!(
+            run(Quote::C, &["tab\there", "\"q\" \\", "é??!\x01"]),
+            r#""tab\there" "\"q\" \\" "\303\251?\?!\001""#
+        );
+    }
+}
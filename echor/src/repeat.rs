@@ -0,0 +1,221 @@
+use std::{
+    io::{self, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The size of the buffer lines are copied into for `--repeat`, so that
+/// each write carries many lines.
+const BUF_SIZE: usize = 128 * 1024;
+
+/// How many times `--repeat` writes the line.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Repeat {
+    Times(u64),
+    Forever,
+}
+
+impl Repeat {
+    /// Parses a count or `inf`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "inf" => Ok(Self::Forever),
+            value => value
+                .parse()
+                .map(Self::Times)
+                .map_err(|_| format!("expected a count or inf, not '{value}'")),
+        }
+    }
+
+    fn limit(self) -> u64 {
+        match self {
+            Self::Times(n) => n,
+            Self::Forever => u64::MAX,
+        }
+    }
+}
+
+/// Parses `--rate`, a positive number of lines per second.
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|rate: &f64| rate.is_finite() && *rate > 0.0)
+        .ok_or_else(|| format!("expected a positive number of lines per second, not '{value}'"))
+}
+
+/// What a repeated line consists of.
+pub struct Line<'a> {
+    pub text: &'a [u8],
+    /// Written after the sequence number, if any.
+    pub ending: &'a [u8],
+    /// Placed before a sequence number counting from 1, when lines are
+    /// numbered.
+    pub count_separator: Option<&'a [u8]>,
+}
+
+/// Writes `line` to `out` `repeat` times, at most `rate` lines a second.
+pub fn repeat(
+    line: &Line,
+    repeat: Repeat,
+    rate: Option<f64>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match (rate, line.count_separator) {
+        (Some(rate), _) => throttled(line, repeat, rate, out),
+        (None, Some(_)) => numbered(line, repeat, out),
+        (None, None) => plain(line, repeat, out),
+    }
+}
+
+/// Fills a buffer with whole lines once and writes it until done.
+fn plain(line: &Line, repeat: Repeat, out: &mut impl Write) -> io::Result<()> {
+    let len = line.text.len() + line.ending.len();
+    if len == 0 {
+        return Ok(());
+    }
+    let per_buf = (BUF_SIZE / len).max(1);
+    let mut buf = Vec::with_capacity(per_buf * len);
+    for _ in 0..per_buf {
+        buf.extend_from_slice(line.text);
+        buf.extend_from_slice(line.ending);
+    }
+
+    let mut left = repeat.limit();
+    while left > 0 {
+        let lines = left.min(per_buf as u64);
+        out.write_all(&buf[..lines as usize * len])?;
+        if repeat != Repeat::Forever {
+            left -= lines;
+        }
+    }
+    out.flush()
+}
+
+fn write_line(line: &Line, n: u64, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(line.text);
+    if let Some(separator) = line.count_separator {
+        buf.extend_from_slice(separator);
+        buf.extend_from_slice(n.to_string().as_bytes());
+    }
+    buf.extend_from_slice(line.ending);
+}
+
+/// Builds numbered lines into a buffer and writes it whenever it is full.
+fn numbered(line: &Line, repeat: Repeat, out: &mut impl Write) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(BUF_SIZE + line.text.len() + 32);
+    for n in 1..=repeat.limit() {
+        write_line(line, n, &mut buf);
+        if buf.len() >= BUF_SIZE {
+            out.write_all(&buf)?;
+            buf.clear();
+        }
+    }
+    out.write_all(&buf)?;
+    out.flush()
+}
+
+/// Writes each line when it is due, keeping to the rate over the whole run
+/// rather than between neighbouring lines.
+fn throttled(line: &Line, repeat: Repeat, rate: f64, out: &mut impl Write) -> io::Result<()> {
+    let start = Instant::now();
+    let mut buf = Vec::new();
+    for n in 1..=repeat.limit() {
+        match due(start, n, rate) {
+            Some(due) => {
+                if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+            }
+            // So slow a rate that the line never comes due.
+            None => loop {
+                thread::sleep(Duration::MAX);
+            },
+        }
+        buf.clear();
+        write_line(line, n, &mut buf);
+        out.write_all(&buf)?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// When the `n`th line is due at `rate` lines a second, or `None` if that is
+/// further off than an `Instant` reaches.
+fn due(start: Instant, n: u64, rate: f64) -> Option<Instant> {
+    Duration::try_from_secs_f64((n - 1) as f64 / rate)
+        .ok()
+        .and_then(|delay| start.checked_add(delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{due, parse_rate, repeat, Line, Repeat, BUF_SIZE};
+    use std::time::{Duration, Instant};
+
+    fn run(line: &Line, times: u64, rate: Option<f64>) -> Vec<u8> {
+        let mut out = Vec::new();
+        repeat(line, Repeat::Times(times), rate, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_repeat() {
+        let line = Line {
+            text: b"y",
+            ending: b"\n",
+            count_separator: None,
+        };
+        assert_eq!(run(&line, 3, None), b"y\ny\ny\n");
+        assert_eq!(run(&line, 0, None), b"");
+        let many = run(&line, BUF_SIZE as u64 + 1, None);
+        assert_eq!(many.len(), 2 * (BUF_SIZE + 1));
+        assert!(many.chunks(2).all(|chunk| chunk == b"y\n"));
+
+        let long = vec![b'x'; BUF_SIZE + 1];
+        let line = Line {
+            text: &long,
+            ending: b"",
+            count_separator: None,
+        };
+        assert_eq!(run(&line, 2, None).len(), 2 * long.len());
+
+        let line = Line {
+            text: b"req",
+            ending: b"\n",
+            count_separator: Some(b" #"),
+        };
+        assert_eq!(run(&line, 3, None), b"req #1\nreq #2\nreq #3\n");
+        let numbered = run(&line, 20_000, None);
+        assert!(numbered.ends_with(b"req #19999\nreq #20000\n"));
+    }
+
+    #[test]
+    fn test_rate() {
+        let line = Line {
+            text: b"tick",
+            ending: b"\n",
+            count_separator: Some(b" "),
+        };
+        let start = Instant::now();
+        assert_eq!(run(&line, 3, Some(20.0)), b"tick 1\ntick 2\ntick 3\n");
+        assert!(start.elapsed().as_secs_f64() >= 0.1);
+
+        assert_eq!(due(start, 1, 1e-20), Some(start));
+        assert_eq!(due(start, 3, 2.0), Some(start + Duration::from_secs(1)));
+        assert_eq!(due(start, 2, 1e-20), None);
+        assert_eq!(due(start, u64::MAX, f64::MIN_POSITIVE), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Repeat::parse("5"), Ok(Repeat::Times(5)));
+        assert_eq!(Repeat::parse("inf"), Ok(Repeat::Forever));
+        assert!(Repeat::parse("-1").is_err());
+        assert!(Repeat::parse("many").is_err());
+        assert_eq!(parse_rate("0.5"), Ok(0.5));
+        assert!(parse_rate("0").is_err());
+        assert!(parse_rate("inf").is_err());
+        assert!(parse_rate("fast").is_err());
+    }
+}
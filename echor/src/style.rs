@@ -0,0 +1,304 @@
+use std::io::{self, Write};
+
+use clap::{builder::PossibleValue, ValueEnum};
+
+/// One of the eight standard terminal colors.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl ValueEnum for Color {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            Self::Black,
+            Self::Red,
+            Self::Green,
+            Self::Yellow,
+            Self::Blue,
+            Self::Magenta,
+            Self::Cyan,
+            Self::White,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(PossibleValue::new(match self {
+            Self::Black => "black",
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Yellow => "yellow",
+            Self::Blue => "blue",
+            Self::Magenta => "magenta",
+            Self::Cyan => "cyan",
+            Self::White => "white",
+        }))
+    }
+}
+
+/// Colors and attributes to write text in.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub underline: bool,
+}
+
+const RESET: &[u8] = b"\x1b[0m";
+
+impl Style {
+    /// The SGR escape selecting this style, empty for the default style.
+    fn sgr(&self) -> Vec<u8> {
+        let mut codes = vec![];
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some(fg) = self.fg {
+            codes.push((30 + fg as u8).to_string());
+        }
+        if let Some(bg) = self.bg {
+            codes.push((40 + bg as u8).to_string());
+        }
+        if codes.is_empty() {
+            return vec![];
+        }
+        format!("\x1b[{}m", codes.join(";")).into_bytes()
+    }
+
+    /// This style with the settings in a markup tag such as `bold,red` or
+    /// `bg:blue` added, or None if the tag is not one.
+    fn with_tag(mut self, tag: &[u8]) -> Option<Self> {
+        for word in std::str::from_utf8(tag).ok()?.split(',') {
+            match word.trim() {
+                "bold" => self.bold = true,
+                "underline" => self.underline = true,
+                word => match word.strip_prefix("bg:") {
+                    Some(name) => self.bg = Some(Color::from_str(name, true).ok()?),
+                    None => self.fg = Some(Color::from_str(word, true).ok()?),
+                },
+            }
+        }
+        Some(self)
+    }
+}
+
+/// Writes styles to `out` as they change, or nothing when `color` is off.
+struct Painter<'a> {
+    out: &'a mut Vec<u8>,
+    color: bool,
+    styled: bool,
+}
+
+impl Painter<'_> {
+    fn set(&mut self, style: Style) {
+        if !self.color {
+            return;
+        }
+        if self.styled {
+            self.out.extend_from_slice(RESET);
+        }
+        let sgr = style.sgr();
+        self.styled = !sgr.is_empty();
+        self.out.extend_from_slice(&sgr);
+    }
+}
+
+/// Appends `text` to `out` in `style`, ending with a reset if anything was
+/// styled. With `markup`, a tag such as `{red}` or `{bold,bg:blue}` adds to
+/// the style until the matching `{/}`, and `{{` is a literal `{`; other
+/// braces are written as they are. Without `color`, tags are still removed
+/// but no styles are written.
+pub fn render(text: &[u8], style: Style, markup: bool, color: bool, out: &mut Vec<u8>) {
+    let mut painter = Painter {
+        out,
+        color,
+        styled: false,
+    };
+    painter.set(style);
+    let mut stack = vec![style];
+    let mut i = 0;
+    while i < text.len() {
+        if markup && text[i] == b'{' {
+            if text.get(i + 1) == Some(&b'{') {
+                painter.out.push(b'{');
+                i += 2;
+                continue;
+            }
+            let tag = text[i + 1..]
+                .iter()
+                .position(|&b| b == b'}')
+                .map(|len| &text[i + 1..i + 1 + len]);
+            if let Some(tag) = tag {
+                let current = *stack.last().unwrap();
+                let next = match tag {
+                    b"/" => {
+                        // An unmatched {/} is dropped.
+                        if stack.len() > 1 {
+                            stack.pop();
+                        }
+                        Some(*stack.last().unwrap())
+                    }
+                    tag => current.with_tag(tag).inspect(|&next| stack.push(next)),
+                };
+                if let Some(next) = next {
+                    if next != current {
+                        painter.set(next);
+                    }
+                    i += tag.len() + 2;
+                    continue;
+                }
+            }
+        }
+        painter.out.push(text[i]);
+        i += 1;
+    }
+    if painter.styled {
+        painter.out.extend_from_slice(RESET);
+    }
+}
+
+/// Writes text through to `inner` in a style, as [`render`] does without
+/// markup, for text too long to be held at once. The style ends before a
+/// final newline.
+pub struct Styled<W> {
+    inner: W,
+    sgr: Vec<u8>,
+    started: bool,
+    /// A newline held back from the end of the last write.
+    newline: bool,
+}
+
+impl<W: Write> Styled<W> {
+    pub fn new(inner: W, style: Style, color: bool) -> Self {
+        Self {
+            inner,
+            sgr: if color { style.sgr() } else { vec![] },
+            started: false,
+            newline: false,
+        }
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.inner.write_all(&self.sgr)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the style, then any final newline, and flushes `inner`.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.start()?;
+        if !self.sgr.is_empty() {
+            self.inner.write_all(RESET)?;
+        }
+        if self.newline {
+            self.inner.write_all(b"\n")?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for Styled<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.start()?;
+        if self.newline {
+            self.inner.write_all(b"\n")?;
+            self.newline = false;
+        }
+        let body = buf.strip_suffix(b"\n").unwrap_or(buf);
+        self.inner.write_all(body)?;
+        self.newline = body.len() < buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, Color, Style, Styled};
+    use std::io::Write;
+
+    fn run(text: &str, style: Style, markup: bool, color: bool) -> String {
+        let mut out = Vec::new();
+        render(text.as_bytes(), style, markup, color, &mut out);
+        String::from_utf8(out).unwrap()
+    }
+
+    fn styled(parts: &[&str], style: Style, color: bool) -> String {
+        let mut out = Vec::new();
+        let mut styled = Styled::new(&mut out, style, color);
+        for part in parts {
+            styled.write_all(part.as_bytes()).unwrap();
+        }
+        styled.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_styled() {
+        let red = Style {
+            fg: Some(Color::Red),
+            ..Style::default()
+        };
+        assert_eq!(styled(&["a\n", "b\n"], red, true), "\x1b[31ma\nb\x1b[0m\n");
+        assert_eq!(styled(&["a\n", ""], red, true), "\x1b[31ma\x1b[0m\n");
+        assert_eq!(styled(&[], red, true), "\x1b[31m\x1b[0m");
+        assert_eq!(styled(&["a\n"], red, false), "a\n");
+        assert_eq!(styled(&["a\n"], Style::default(), true), "a\n");
+    }
+
+    #[test]
+    fn test_render() {
+        let plain = Style::default();
+        assert_eq!(run("a {red}b{/}", plain, false, true), "a {red}b{/}");
+
+        let style = Style {
+            fg: Some(Color::Red),
+            bg: Some(Color::Black),
+            bold: true,
+            underline: true,
+        };
+        assert_eq!(run("ok", style, false, true), "\x1b[1;4;31;40mok\x1b[0m");
+        assert_eq!(run("ok", style, false, false), "ok");
+
+        assert_eq!(
+            run("a {red}FAIL{/} b", plain, true, true),
+            "a \x1b[31mFAIL\x1b[0m b"
+        );
+        assert_eq!(run("a {red}FAIL{/} b", plain, true, false), "a FAIL b");
+        assert_eq!(
+            run("{bold}a{Green,bg:blue}b{/}c{/}d", plain, true, true),
+            "\x1b[1ma\x1b[0m\x1b[1;32;44mb\x1b[0m\x1b[1mc\x1b[0md"
+        );
+        let bold = Style {
+            bold: true,
+            ..plain
+        };
+        assert_eq!(
+            run("a{red}b", bold, true, true),
+            "\x1b[1ma\x1b[0m\x1b[1;31mb\x1b[0m"
+        );
+        assert_eq!(
+            run("{{red} {pink} {bg:} {a {/}", plain, true, true),
+            "{red} {pink} {bg:} {a "
+        );
+    }
+}
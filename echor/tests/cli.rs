@@ -23,6 +23,21 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
     Ok(())
 }
 
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe("echor", ["hello"; 20000])
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--generate", "fish"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("complete -c echor -s n"));
+    Ok(())
+}
+
 #[test]
 fn hello1() -> TestResult {
     run(&["Hello there"], "tests/expected/hello1.txt")
@@ -42,3 +57,301 @@ fn hello1_no_newline() -> TestResult {
 fn hello2_no_newline() -> TestResult {
     run(&["-n", "Hello", "there"], "tests/expected/hello2.n.txt")
 }
+
+#[test]
+fn escapes() -> TestResult {
+    run(
+        &["-e", "tab\\there", "\\\\back\\x41\\0101\\u00e9"],
+        "tests/expected/escapes.e.txt",
+    )
+}
+
+#[test]
+fn escapes_stop_output() -> TestResult {
+    run(&["-e", "stop\\cped", "here"], "tests/expected/stop.e.txt")
+}
+
+#[test]
+fn escapes_disabled() -> TestResult {
+    run(&["-e", "-E", "tab\\there"], "tests/expected/escapes.eE.txt")
+}
+
+#[test]
+fn format_reused() -> TestResult {
+    run(
+        &["--format", "%s=%d\\n", "a", "1", "b", "-2", "c"],
+        "tests/expected/format.txt",
+    )
+}
+
+#[test]
+fn format_specs() -> TestResult {
+    run(
+        &[
+            "--format",
+            "%-5s|%5.2f|%#x|%b|%c\\n",
+            "ab",
+            "3.14159",
+            "255",
+            "tab\\there",
+            "xyz",
+        ],
+        "tests/expected/format_specs.txt",
+    )
+}
+
+#[test]
+fn format_bad_number() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--format", "%d,%d\\n", "x", "2"])
+        .assert()
+        .code(1)
+        .stdout("0,2\n")
+        .stderr("'x': expected a numeric value\n");
+    Ok(())
+}
+
+#[test]
+fn format_invalid_spec() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--format", "ab%ycd", "x"])
+        .assert()
+        .code(1)
+        .stdout("ab")
+        .stderr("%y: invalid conversion specification\n");
+    Ok(())
+}
+
+#[test]
+fn format_excess_args() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--format", "x\\n", "a", "b"])
+        .assert()
+        .success()
+        .stdout("x\n")
+        .stderr("warning: ignoring excess arguments, starting with 'a'\n");
+    Ok(())
+}
+
+#[test]
+fn dies_format_with_text() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["-n", "--format", "%s", "a"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+#[test]
+fn styles() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--fg", "red", "--bg", "black", "--bold", "--underline"])
+        .args(["--color", "always", "ok"])
+        .assert()
+        .success()
+        .stdout("\x1b[1;4;31;40mok\x1b[0m\n");
+    Ok(())
+}
+
+#[test]
+fn markup() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args([
+            "--markup",
+            "--color=always",
+            "{green}ok{/} {bold,red}FAIL{/}",
+        ])
+        .assert()
+        .success()
+        .stdout("\x1b[32mok\x1b[0m \x1b[1;31mFAIL\x1b[0m\n");
+    Ok(())
+}
+
+#[test]
+fn styles_off_when_piped() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--fg", "red", "--markup", "{bold}FAIL{/} {x}"])
+        .assert()
+        .success()
+        .stdout("FAIL {x}\n");
+    Ok(())
+}
+
+#[test]
+fn expand() -> TestResult {
+    Command::cargo_bin("echor")?
+        .env("APP", "web")
+        .env_remove("TARGET")
+        .args([
+            "--expand",
+            "${APP}",
+            "to",
+            "${TARGET:-staging}",
+            "$APP",
+            "$$",
+        ])
+        .assert()
+        .success()
+        .stdout("web to staging $APP $\n");
+    Ok(())
+}
+
+#[test]
+fn template() -> TestResult {
+    Command::cargo_bin("echor")?
+        .env("APP", "web")
+        .env("TARGET", "prod")
+        .env("DEPLOYER", "ann")
+        .args(["--template", "tests/inputs/deploy.tmpl"])
+        .assert()
+        .success()
+        .stdout("Deploying web to prod\nby ann, costs $0\n");
+    Ok(())
+}
+
+#[test]
+fn template_missing_var() -> TestResult {
+    Command::cargo_bin("echor")?
+        .env("APP", "web")
+        .env_remove("DEPLOYER")
+        .args(["--template", "tests/inputs/deploy.tmpl"])
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr("DEPLOYER: set DEPLOYER\n");
+    Ok(())
+}
+
+#[test]
+fn quote_shell() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--quote=shell", "cat", "my file", "it's"])
+        .assert()
+        .success()
+        .stdout("cat 'my file' 'it'\\''s'\n");
+    Ok(())
+}
+
+#[test]
+fn quote_json() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--quote=json", "a \"b\""])
+        .assert()
+        .success()
+        .stdout("\"a \\\"b\\\"\"\n");
+    Command::cargo_bin("echor")?
+        .args(["--quote=json", "-e", "a\\tb", "c"])
+        .assert()
+        .success()
+        .stdout("[\"a\\tb\",\"c\"]\n");
+    Ok(())
+}
+
+#[test]
+fn quote_csv() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--quote=csv", "id", "name, full", "say \"hi\""])
+        .assert()
+        .success()
+        .stdout("id,\"name, full\",\"say \"\"hi\"\"\"\n");
+    Ok(())
+}
+
+#[test]
+fn quote_c() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--quote=c", "-e", "line\\n", "\\x7f"])
+        .assert()
+        .success()
+        .stdout("\"line\\n\" \"\\177\"\n");
+    Ok(())
+}
+
+#[test]
+fn separator() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--separator", ", ", "a", "b", "c"])
+        .assert()
+        .success()
+        .stdout("a, b, c\n");
+    Ok(())
+}
+
+#[test]
+fn dies_escapes_with_compat() -> TestResult {
+    for flag in ["-e", "-E"] {
+        Command::cargo_bin("echor")?
+            .args(["--no-config", "--compat", "posix", flag, "a"])
+            .assert()
+            .code(2)
+            .stderr("-e and -E cannot be used with --compat bsd or posix\n");
+    }
+    Ok(())
+}
+
+#[test]
+fn dies_separator_with_json() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--quote=json", "--separator=;", "a"])
+        .assert()
+        .code(2)
+        .stderr("--separator cannot be used with --quote=json\n");
+    Ok(())
+}
+
+#[test]
+fn repeat() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--repeat", "3", "-e", "a\\tb"])
+        .assert()
+        .success()
+        .stdout("a\tb\n".repeat(3));
+    Ok(())
+}
+
+#[test]
+fn repeat_forever() -> TestResult {
+    common::testing::run_closed_pipe("echor", ["--repeat", "inf", "y"])
+}
+
+#[test]
+fn repeat_count_suffix() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args([
+            "--repeat",
+            "3",
+            "--count-suffix",
+            "--separator=,",
+            "id",
+            "req",
+        ])
+        .assert()
+        .success()
+        .stdout("id,req,1\nid,req,2\nid,req,3\n");
+    Ok(())
+}
+
+#[test]
+fn repeat_rate() -> TestResult {
+    let start = std::time::Instant::now();
+    Command::cargo_bin("echor")?
+        .args(["--repeat", "3", "--rate", "20", "tick"])
+        .assert()
+        .success()
+        .stdout("tick\n".repeat(3));
+    assert!(start.elapsed().as_secs_f64() >= 0.1);
+    Ok(())
+}
+
+#[test]
+fn repeat_tiny_rate() -> TestResult {
+    // The second line is due long after the test gives up waiting.
+    Command::cargo_bin("echor")?
+        .args(["--repeat", "2", "--rate", "1e-20", "y"])
+        .timeout(std::time::Duration::from_millis(500))
+        .assert()
+        .interrupted()
+        .stdout("y\n");
+    Ok(())
+}
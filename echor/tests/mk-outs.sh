@@ -1,9 +1,16 @@
 #!/usr/bin/env bash
 
 OUTDIR="tests/expected"
+# A UTF-8 locale, for the \u escape
+export LC_ALL=C.UTF-8
 [[ ! -d "$OUTDIR" ]] && mkdir -p "$OUTDIR"
 
 echo "Hello there" >$OUTDIR/hello1.txt
 echo "Hello" "there" >$OUTDIR/hello2.txt
 echo -n "Hello  there" >$OUTDIR/hello1.n.txt
 echo -n "Hello" "there" >$OUTDIR/hello2.n.txt
+echo -e 'tab\there' '\\back\x41\0101\u00e9' >$OUTDIR/escapes.e.txt
+echo -e 'stop\cped' "here" >$OUTDIR/stop.e.txt
+echo -e -E 'tab\there' >$OUTDIR/escapes.eE.txt
+printf '%s=%d\n' a 1 b -2 c >$OUTDIR/format.txt
+printf '%-5s|%5.2f|%#x|%b|%c\n' ab 3.14159 255 'tab\there' xyz >$OUTDIR/format_specs.txt
@@ -1,11 +1,23 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs::FileType,
+    io::{self, Write},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
 use clap::{builder::PossibleValue, Arg, ArgAction, Command, ValueEnum};
+use common::{
+    archive::{self, Member, MemberType},
+    Error, Failures, Format, JsonWriter, Result,
+};
 use regex::Regex;
+use serde::Serialize;
 use walkdir::{DirEntry, WalkDir};
 
-type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
-
 #[derive(Debug, Clone, Eq, PartialEq)]
-enum EntryType {
+pub enum EntryType {
     Dir,
     File,
     Link,
@@ -18,22 +30,170 @@ impl ValueEnum for EntryType {
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
         Some(match self {
-            Self::Dir => PossibleValue::new("d"),
-            Self::File => PossibleValue::new("f"),
-            Self::Link => PossibleValue::new("l"),
+            Self::Dir => PossibleValue::new("d").help("Directory"),
+            Self::File => PossibleValue::new("f").help("Regular file"),
+            Self::Link => PossibleValue::new("l").help("Symbolic link"),
         })
     }
 }
 
+/// The JSON object for each entry. `size` and `mtime`, in seconds since
+/// the Unix epoch, are null when the entry's metadata cannot be read.
+#[derive(Debug, Serialize)]
+struct Entry {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: &'static str,
+    size: Option<u64>,
+    mtime: Option<i64>,
+}
+
+impl EntryType {
+    fn of_file(file_type: FileType) -> Option<Self> {
+        if file_type.is_symlink() {
+            Some(Self::Link)
+        } else if file_type.is_dir() {
+            Some(Self::Dir)
+        } else if file_type.is_file() {
+            Some(Self::File)
+        } else {
+            None
+        }
+    }
+
+    fn of_member(member_type: MemberType) -> Option<Self> {
+        match member_type {
+            MemberType::Dir => Some(Self::Dir),
+            MemberType::File => Some(Self::File),
+            MemberType::Link => Some(Self::Link),
+            MemberType::Other => None,
+        }
+    }
+
+    fn json_name(entry_type: Option<&Self>) -> &'static str {
+        match entry_type {
+            Some(Self::Dir) => "dir",
+            Some(Self::File) => "file",
+            Some(Self::Link) => "link",
+            None => "other",
+        }
+    }
+}
+
+impl Entry {
+    fn new(entry: &DirEntry) -> Self {
+        let metadata = entry.metadata().ok();
+        Self {
+            path: entry.path().display().to_string(),
+            entry_type: EntryType::json_name(EntryType::of_file(entry.file_type()).as_ref()),
+            size: metadata.as_ref().map(|m| m.len()),
+            mtime: metadata.and_then(|m| m.modified().ok()).map(|t| {
+                match t.duration_since(UNIX_EPOCH) {
+                    Ok(after) => after.as_secs() as i64,
+                    Err(before) => -(before.duration().as_secs() as i64),
+                }
+            }),
+        }
+    }
+
+    fn from_member(path: String, member: &Member) -> Self {
+        Self {
+            path,
+            entry_type: EntryType::json_name(EntryType::of_member(member.member_type).as_ref()),
+            size: Some(member.size),
+            mtime: Some(member.mtime),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Config {
     paths: Vec<String>,
     names: Vec<Regex>,
     entry_types: Vec<EntryType>,
+    terminator: u8,
+    format: Format,
+    archives: bool,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("findr")
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+/// An entry is printed if it matches any of the names and any of the
+/// types; an empty list matches everything.
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    paths: Vec<String>,
+    names: Vec<Regex>,
+    entry_types: Vec<EntryType>,
+    print0: bool,
+    format: Format,
+    archives: bool,
+}
+
+impl ConfigBuilder {
+    /// Directories to search. Defaults to ".".
+    pub fn paths<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.paths = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn names(mut self, names: impl IntoIterator<Item = Regex>) -> Self {
+        self.names = names.into_iter().collect();
+        self
+    }
+
+    pub fn entry_types(mut self, entry_types: impl IntoIterator<Item = EntryType>) -> Self {
+        self.entry_types = entry_types.into_iter().collect();
+        self
+    }
+
+    /// End each path with NUL instead of newline, for `xargs -0` and the
+    /// `-z` mode of the other tools.
+    pub fn print0(mut self, print0: bool) -> Self {
+        self.print0 = print0;
+        self
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Also list the members of tar and zip files, as `archive//member`,
+    /// as if the archives were directories.
+    pub fn archives(mut self, archives: bool) -> Self {
+        self.archives = archives;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        Ok(Config {
+            paths: if self.paths.is_empty() {
+                vec![".".to_string()]
+            } else {
+                self.paths
+            },
+            names: self.names,
+            entry_types: self.entry_types,
+            terminator: common::terminator(self.print0),
+            format: self.format,
+            archives: self.archives,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("findr")
         .version("0.1.0")
         .author("Talentaa <talentaa@qq.com>")
         .about("Rust find")
@@ -64,66 +224,164 @@ pub fn get_args() -> MyResult<Config> {
                 .num_args(0..)
                 .help("Entry type"),
         )
-        .get_matches();
-
-    Ok(Config {
-        paths: matches
-            .get_many("paths")
-            .expect("paths required")
-            .cloned()
-            .collect(),
-
-        names: matches
-            .get_many("names")
-            .unwrap_or_default()
-            .cloned()
-            .collect(),
-
-        entry_types: matches
-            .get_many("entry_types")
-            .unwrap_or_default()
-            .cloned()
-            .collect(),
-    })
-}
-
-pub fn run(config: Config) -> MyResult<()> {
-    let type_filter = |entry: &DirEntry| {
-        config.entry_types.is_empty()
-            || config
-                .entry_types
-                .iter()
-                .any(|entry_type| match entry_type {
-                    EntryType::Link => entry.file_type().is_symlink(),
-                    EntryType::Dir => entry.file_type().is_dir(),
-                    EntryType::File => entry.file_type().is_file(),
-                })
-    };
+        .arg(
+            Arg::new("print0")
+                .long("print0")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["json", "ndjson"])
+                .help("Separate paths with NUL instead of newline"),
+        )
+        .arg(
+            Arg::new("archives")
+                .long("archives")
+                .action(ArgAction::SetTrue)
+                .help("Search inside tar and zip files"),
+        )
+        .args(Format::args())
+}
 
-    let name_filter = |entry: &DirEntry| {
-        config.names.is_empty()
-            || config
-                .names
-                .iter()
-                .any(|re| re.is_match(&entry.file_name().to_string_lossy()))
-    };
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
 
-    for path in config.paths {
-        let entries: Vec<String> = WalkDir::new(path)
-            .into_iter()
-            .filter_map(|e| match e {
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+
+    Config::builder()
+        .paths(
+            matches
+                .get_many::<String>("paths")
+                .expect("paths required")
+                .cloned(),
+        )
+        .names(matches.get_many("names").unwrap_or_default().cloned())
+        .entry_types(matches.get_many("entry_types").unwrap_or_default().cloned())
+        .print0(matches.get_flag("print0"))
+        .format(Format::from_matches(&matches))
+        .archives(matches.get_flag("archives"))
+        .build()
+}
+
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::stdout())
+}
+
+/// Writes the matching entries under each of `config`'s paths to `out`.
+pub fn run_with(config: &Config, mut out: impl Write) -> Result<()> {
+    let mut failures = Failures::default();
+    let mut json = JsonWriter::new(config.format);
+    for path in &config.paths {
+        for entry in WalkDir::new(path) {
+            match entry {
                 Err(e) => {
-                    eprintln!("{}", e);
-                    None
+                    let path = e.path().unwrap_or(Path::new("")).display().to_string();
+                    let msg = e.to_string();
+                    let source = e.into_io_error().unwrap_or_else(|| io::Error::other(msg));
+                    failures.report(Error::read(&path, source))?;
                 }
-                Ok(entry) => Some(entry),
-            })
-            .filter(type_filter)
-            .filter(name_filter)
-            .map(|entry| entry.path().display().to_string())
-            .collect();
+                Ok(entry) => {
+                    let entry_type = EntryType::of_file(entry.file_type());
+                    let name = entry.file_name().to_string_lossy();
+                    if matches(config, entry_type, &name) {
+                        if config.format.is_text() {
+                            out.write_all(entry.path().as_os_str().as_encoded_bytes())?;
+                            out.write_all(&[config.terminator])?;
+                        } else {
+                            json.write(&mut out, &Entry::new(&entry))?;
+                        }
+                    }
+                    if config.archives && entry.file_type().is_file() {
+                        if let Err(err) = find_members(entry.path(), config, &mut json, &mut out) {
+                            failures.report(err)?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    json.finish(&mut out)?;
+    out.flush()?;
+    failures.finish()
+}
+
+/// An entry matches if it has any of the types and any of the names.
+fn matches(config: &Config, entry_type: Option<EntryType>, name: &str) -> bool {
+    (config.entry_types.is_empty()
+        || entry_type.is_some_and(|entry_type| config.entry_types.contains(&entry_type)))
+        && (config.names.is_empty() || config.names.iter().any(|re| re.is_match(name)))
+}
 
-        println!("{}", entries.join("\n"));
+/// Lists the matching members of `path` if it is an archive.
+fn find_members(
+    path: &Path,
+    config: &Config,
+    json: &mut JsonWriter,
+    out: &mut impl Write,
+) -> Result<()> {
+    let Some(archive) = path.to_str() else {
+        return Ok(());
+    };
+    let members = archive::members(archive).map_err(|e| Error::read(archive, e))?;
+    for member in members.iter().flatten() {
+        let name = member.path.rsplit('/').next().unwrap_or_default();
+        if !matches(config, EntryType::of_member(member.member_type), name) {
+            continue;
+        }
+        let path = format!("{archive}{}{}", archive::SEPARATOR, member.path);
+        if config.format.is_text() {
+            out.write_all(path.as_bytes())?;
+            out.write_all(&[config.terminator])?;
+        } else {
+            json.write(out, &Entry::from_member(path, member))?;
+        }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config, EntryType};
+    use common::Format;
+    use regex::Regex;
+
+    #[test]
+    fn test_run_with() {
+        let config = Config::builder()
+            .paths(["tests/inputs/a"])
+            .names([Regex::new(r"\.csv$").unwrap()])
+            .entry_types([EntryType::File])
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        assert_eq!(String::from_utf8(out).unwrap(), "tests/inputs/a/b/b.csv\n");
+
+        let config = Config::builder()
+            .paths(["tests/inputs/a/b/c"])
+            .entry_types([EntryType::File])
+            .print0(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        assert_eq!(out, b"tests/inputs/a/b/c/c.mp3\0");
+
+        let config = Config::builder()
+            .paths(["tests/inputs/a/b/c"])
+            .format(Format::Ndjson)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, &mut out).is_ok());
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(r#"{"path":"tests/inputs/a/b/c","type":"dir","size":"#));
+        assert!(lines[1]
+            .starts_with(r#"{"path":"tests/inputs/a/b/c/c.mp3","type":"file","size":2,"mtime":"#));
+    }
+}
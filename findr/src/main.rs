@@ -1,6 +1,5 @@
 fn main() {
     if let Err(e) = findr::get_args().and_then(findr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+        e.exit();
     }
 }
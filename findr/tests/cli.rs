@@ -30,11 +30,120 @@ fn skips_bad_dir() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
+        .failure()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
+// --------------------------------------------------
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, ["tests/inputs"; 1000])
+}
+
+#[test]
+fn archives() -> TestResult {
+    run(
+        &["tests/archives", "--archives"],
+        "tests/expected/archives.txt",
+    )
+}
+
+#[test]
+fn archives_type_d() -> TestResult {
+    run(
+        &["tests/archives", "--archives", "--type", "d"],
+        "tests/expected/archives_type_d.txt",
+    )
+}
+
+#[test]
+fn archives_name_csv() -> TestResult {
+    run(
+        &["tests/archives", "--archives", "--name", ".*[.]csv"],
+        "tests/expected/archives_name_csv.txt",
+    )
+}
+
+#[test]
+fn archives_skips_undecodable() -> TestResult {
+    // bad.gz is cut short, so it decodes to nothing.
+    Command::cargo_bin(PRG)?
+        .args(["tests/corrupt", "--archives"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("tests/corrupt/bad.gz\n")
+                .and(predicate::str::contains("//").not()),
+        )
+        .stderr("");
+    Ok(())
+}
+
+#[test]
+fn archives_not_listed_by_default() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("tests/archives")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("//").not());
+    Ok(())
+}
+
+#[test]
+fn ndjson() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b/c", "--type", "f", "--ndjson"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            r#"^\{"path":"tests/inputs/a/b/c/c.mp3","type":"file","size":2,"mtime":\d+\}\n$"#,
+        )?);
+    Ok(())
+}
+
+#[test]
+fn print0() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a/b/c", "--type", "f", "--print0"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"tests/inputs/a/b/c/c.mp3\0"[..]));
+    Ok(())
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#"compgen -W "d f l""#));
+    Ok(())
+}
+
+#[test]
+fn generates_man_page() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "man"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("l: Symbolic link"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_generate() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "tcsh"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("possible values"));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_bad_name() -> TestResult {
@@ -60,14 +169,14 @@ fn dies_bad_type() -> TestResult {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -291,13 +400,10 @@ fn unreadable_dir() -> TestResult {
         .status()
         .expect("failed");
 
-    let cmd = Command::cargo_bin(PRG)?
-        .arg("tests/inputs")
-        .assert()
-        .success();
+    let out = Command::cargo_bin(PRG)?.arg("tests/inputs").output()?;
     fs::remove_dir(dirname)?;
 
-    let out = cmd.get_output();
+    assert_eq!(out.status.code(), Some(1));
     let stdout = String::from_utf8(out.stdout.clone())?;
     let lines: Vec<&str> = stdout.split('\n').filter(|s| !s.is_empty()).collect();
 
@@ -1,21 +1,124 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, Read},
+    env,
+    ffi::OsString,
+    io::{BufRead, Read, Write},
 };
 
 use clap::{Arg, Command};
-
-type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
+use common::{Compat, Error, Failures, Format, Inputs, JsonWriter, Record, Records, Result};
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
     lines: u64,
     bytes: Option<u64>,
+    terminator: u8,
+    format: Format,
+    compat: Option<Compat>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("headr")
+#[derive(Debug)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    lines: u64,
+    bytes: Option<u64>,
+    zero_terminated: bool,
+    format: Format,
+    compat: Option<Compat>,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            lines: 10,
+            bytes: None,
+            zero_terminated: false,
+            format: Format::Text,
+            compat: None,
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Number of lines to print from each file, 10 by default.
+    pub fn lines(mut self, lines: u64) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// Print this many bytes instead of whole lines.
+    pub fn bytes(mut self, bytes: Option<u64>) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    /// Count records separated by NUL instead of lines.
+    pub fn zero_terminated(mut self, zero_terminated: bool) -> Self {
+        self.zero_terminated = zero_terminated;
+        self
+    }
+
+    /// Output format. JSON output has one object per record, so it cannot
+    /// be combined with `bytes`.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Write file headers as another implementation does.
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.lines == 0 || self.bytes == Some(0) {
+            return Err(Error::Usage(
+                "line and byte counts must be at least 1".to_string(),
+            ));
+        }
+        if self.bytes.is_some() && !self.format.is_text() {
+            return Err(Error::Usage(
+                "cannot write bytes as JSON records".to_string(),
+            ));
+        }
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            lines: self.lines,
+            bytes: self.bytes,
+            terminator: common::terminator(self.zero_terminated),
+            format: self.format,
+            compat: self.compat,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("headr")
         .version("0.1.0")
         .author("Talentaa <talentaa@qq.com>")
         .about("Rust head")
@@ -45,60 +148,188 @@ pub fn get_args() -> MyResult<Config> {
                 .value_parser(clap::value_parser!(u64).range(1..))
                 .default_value("10"),
         )
-        .get_matches();
-
-    Ok(Config {
-        files: matches
-            .get_many("files")
-            .expect("file required")
-            .cloned()
-            .collect(),
-        lines: matches.get_one("lines").cloned().unwrap(),
-        bytes: matches.get_one("bytes").cloned(),
-    })
+        .arg(common::zero_terminated_arg())
+        .args(Format::args())
+        .arg(Compat::arg())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+
+    Config::builder()
+        .files(
+            matches
+                .get_many::<String>("files")
+                .expect("file required")
+                .cloned(),
+        )
+        .lines(matches.get_one("lines").cloned().unwrap())
+        .bytes(matches.get_one("bytes").cloned())
+        .zero_terminated(matches.get_flag("zero_terminated"))
+        .format(Format::from_matches(&matches))
+        .compat(Compat::from_matches(&matches))
+        .build()
 }
 
-pub fn run(config: Config) -> MyResult<()> {
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::open, common::stdout())
+}
+
+/// Writes the head of each of `config`'s files, opened through `inputs`,
+/// to `out`.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, mut out: impl Write) -> Result<()> {
     let num_files = config.files.len();
+    let mut failures = Failures::default();
+    let mut json = JsonWriter::new(config.format);
+    let mut headers = 0;
 
     for (file_num, filename) in config.files.iter().enumerate() {
-        match open(filename) {
-            Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(mut file) => {
-                if num_files > 1 {
-                    println!(
-                        "{}==> {} <==",
-                        if file_num > 0 { "\n" } else { "" },
-                        &filename
-                    );
+        match inputs.open(filename) {
+            Err(err) => failures.report(Error::open(filename, err))?,
+            Ok(file) => {
+                if num_files > 1 && config.format.is_text() {
+                    // Other implementations only separate headers that are
+                    // written, skipping files that could not be opened.
+                    let separate = match config.compat {
+                        None => file_num > 0,
+                        Some(_) => headers > 0,
+                    };
+                    let name = match (config.compat, filename.as_str()) {
+                        (Some(Compat::Gnu), "-") => "standard input",
+                        _ => filename,
+                    };
+                    writeln!(out, "{}==> {} <==", if separate { "\n" } else { "" }, name)?;
+                    headers += 1;
                 }
 
-                if let Some(num_bytes) = config.bytes {
-                    let mut handle = file.take(num_bytes);
-                    let mut buffer = vec![0; num_bytes as usize];
-                    let bytes_read = handle.read(&mut buffer)?;
-                    print!("{}", String::from_utf8_lossy(&buffer[..bytes_read]));
-                } else {
-                    let mut line = String::new();
-                    for _ in 0..config.lines {
-                        let bytes = file.read_line(&mut line)?;
-                        if bytes == 0 {
-                            break;
-                        }
-
-                        print!("{}", line);
-                        line.clear();
-                    }
+                if let Err(err) = head_file(file, filename, config, &mut json, &mut out) {
+                    failures.report(err)?;
                 }
             }
         }
     }
+    json.finish(&mut out)?;
+    out.flush()?;
+    failures.finish()
+}
+
+fn head_file(
+    file: Box<dyn BufRead>,
+    filename: &str,
+    config: &Config,
+    json: &mut JsonWriter,
+    out: &mut impl Write,
+) -> Result<()> {
+    let read_err = |e| Error::read(filename, e);
+    if let Some(num_bytes) = config.bytes {
+        let mut buffer = Vec::new();
+        file.take(num_bytes)
+            .read_to_end(&mut buffer)
+            .map_err(read_err)?;
+        out.write_all(&buffer)?;
+    } else {
+        let mut records = Records::new(file, config.terminator);
+        let mut line = Vec::new();
+        for record in 1..=config.lines {
+            if !records.read(&mut line).map_err(read_err)? {
+                break;
+            }
+            if config.format.is_text() {
+                out.write_all(&line)?;
+            } else {
+                let fields = vec![String::from_utf8_lossy(records.strip(&line))];
+                json.write(
+                    out,
+                    &Record {
+                        file: filename,
+                        record,
+                        fields,
+                    },
+                )?;
+            }
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config};
+    use common::Format;
+    use std::io::{self, BufRead, Cursor};
+
+    fn inputs(name: &str) -> io::Result<Box<dyn BufRead>> {
+        let text = format!("{name} 1\n{name} 2\n{name} 3\n");
+        Ok(Box::new(Cursor::new(text)))
+    }
+
+    #[test]
+    fn test_run_with() {
+        let config = Config::builder()
+            .files(["a", "b"])
+            .lines(2)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "==> a <==\na 1\na 2\n\n==> b <==\nb 1\nb 2\n"
+        );
+
+        let config = Config::builder().bytes(Some(5)).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"- 1\n-");
+    }
+
+    #[test]
+    fn test_builder() {
+        assert!(Config::builder().lines(0).build().is_err());
+        assert!(Config::builder().bytes(Some(0)).build().is_err());
+        let res = Config::builder()
+            .bytes(Some(1))
+            .format(Format::Json)
+            .build();
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_run_with_ndjson() {
+        let config = Config::builder()
+            .files(["a", "b"])
+            .lines(1)
+            .format(Format::Ndjson)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"file\":\"a\",\"record\":1,\"fields\":[\"a 1\"]}\n\
+            {\"file\":\"b\",\"record\":1,\"fields\":[\"b 1\"]}\n"
+        );
+    }
+
+    #[test]
+    fn test_run_with_zero_terminated() {
+        let config = Config::builder()
+            .lines(2)
+            .zero_terminated(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        let inputs =
+            |_: &str| -> io::Result<Box<dyn BufRead>> { Ok(Box::new(Cursor::new("a\nb\0c\0d\0"))) };
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"a\nb\0c\0");
+    }
+}
@@ -1,6 +1,5 @@
 fn main() {
     if let Err(e) = headr::get_args().and_then(headr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+        e.exit();
     }
 }
@@ -6,6 +6,7 @@ use std::{
 
 use assert_cmd::Command;
 use predicates::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
 
 const PRG: &str = "headr";
 const EMPTY: &str = "./tests/inputs/empty.txt";
@@ -13,20 +14,34 @@ const ONE: &str = "./tests/inputs/one.txt";
 const TWO: &str = "./tests/inputs/two.txt";
 const THREE: &str = "./tests/inputs/three.txt";
 const TEN: &str = "./tests/inputs/ten.txt";
+const LATIN1: &str = "./tests/inputs/latin1.txt";
 
 type TestResult = Result<(), Box<dyn Error>>;
 
+fn gen_bad_file() -> String {
+    loop {
+        let filename: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(7)
+            .map(char::from)
+            .collect();
+
+        if fs::metadata(&filename).is_err() {
+            return filename;
+        }
+    }
+}
+
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let mut file = File::open(expected_file)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
 
     Command::cargo_bin(PRG)?
         .args(args)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(buffer.as_slice()));
     Ok(())
 }
 
@@ -34,15 +49,74 @@ fn run_stdin(args: &[&str], input_file: &str, expected_file: &str) -> TestResult
     let mut file = File::open(expected_file)?;
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
-    let expected = String::from_utf8_lossy(&buffer);
-    let input = fs::read_to_string(input_file)?;
+    let input = fs::read(input_file)?;
 
     Command::cargo_bin(PRG)?
         .args(args)
         .write_stdin(input)
         .assert()
         .success()
-        .stdout(predicate::eq(&expected.as_bytes() as &[u8]));
+        .stdout(predicate::eq(buffer.as_slice()));
+    Ok(())
+}
+
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, [TEN; 2000])
+}
+
+#[test]
+fn ndjson() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--ndjson", "-n", "1", ONE, EMPTY])
+        .assert()
+        .success()
+        .stdout("{\"file\":\"./tests/inputs/one.txt\",\"record\":1,\"fields\":[\"Öne line, four words.\"]}\n");
+    Ok(())
+}
+
+#[test]
+fn dies_json_bytes() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-c", "1", ONE])
+        .assert()
+        .failure()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "1"])
+        .write_stdin("a\nb\0c\0")
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"a\nb\0"[..]));
+    Ok(())
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "zsh"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("#compdef headr"));
+    Ok(())
+}
+
+#[test]
+fn skips_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("{}: .*[(]os error 2[)]", bad);
+    Command::cargo_bin(PRG)?
+        .args([&bad, ONE])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains("==> ./tests/inputs/one.txt <=="))
+        .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
 
@@ -136,6 +210,27 @@ fn one_c4_stdin() -> TestResult {
     run_stdin(&["-c", "4"], ONE, "tests/expected/one.txt.c4.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn latin1() -> TestResult {
+    run(&[LATIN1], "tests/expected/latin1.txt.out")
+}
+
+#[test]
+fn latin1_n2() -> TestResult {
+    run(&[LATIN1, "-n", "2"], "tests/expected/latin1.txt.n2.out")
+}
+
+#[test]
+fn latin1_c4() -> TestResult {
+    run(&[LATIN1, "-c", "4"], "tests/expected/latin1.txt.c4.out")
+}
+
+#[test]
+fn latin1_n4_stdin() -> TestResult {
+    run_stdin(&["-n", "4"], LATIN1, "tests/expected/latin1.txt.n4.out")
+}
+
 // --------------------------------------------------
 #[test]
 fn two() -> TestResult {
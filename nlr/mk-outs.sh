@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+
+set -u
+
+ROOT="tests/inputs"
+OUT_DIR="tests/expected"
+
+[[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
+
+EMPTY="$ROOT/empty.txt"
+FOX="$ROOT/fox.txt"
+BUSTLE="$ROOT/the-bustle.txt"
+BLANKS="$ROOT/blanks.txt"
+PAGES="$ROOT/pages.txt"
+ALL="$EMPTY $FOX $BUSTLE"
+
+for FILE in $ALL $BLANKS $PAGES; do
+    BASENAME=$(basename "$FILE")
+    nl     $FILE > ${OUT_DIR}/${BASENAME}.out
+    nl -ba $FILE > ${OUT_DIR}/${BASENAME}.ba.out
+done
+
+nl $ALL > $OUT_DIR/all.out
+nl < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).stdin.out
+
+nl -ba -l2 $BLANKS > $OUT_DIR/blanks.txt.ba.l2.out
+nl -nln -w3 -s' | ' $BUSTLE > $OUT_DIR/the-bustle.txt.ln.out
+nl -nrz -v10 -i-2 $BUSTLE > $OUT_DIR/the-bustle.txt.rz.out
+
+nl -ha -ft $PAGES > $OUT_DIR/pages.txt.ha.ft.out
+nl -p $PAGES > $OUT_DIR/pages.txt.p.out
+nl -b'p^#' $PAGES > $OUT_DIR/pages.txt.regex.out
+nl -d '' $PAGES > $OUT_DIR/pages.txt.no-sections.out
+sed 's/\\:/@:/g' $PAGES | nl -d@ > $OUT_DIR/pages.txt.d.out
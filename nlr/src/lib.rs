@@ -0,0 +1,580 @@
+use std::{env, ffi::OsString, io::Write, ops::ControlFlow};
+
+use clap::{Arg, ArgAction, Command};
+use common::{Error, Failures, Inputs, Justify, NumberFormat, Records, Result};
+use regex::bytes::Regex;
+
+/// Which lines of a section are numbered, written as `nl -b` takes it.
+#[derive(Debug, Clone)]
+pub enum Style {
+    /// `a`: all lines.
+    All,
+    /// `t`: lines that are not empty.
+    NonEmpty,
+    /// `n`: no lines.
+    None,
+    /// `pREGEX`: lines matching REGEX.
+    Matching(Regex),
+}
+
+impl Style {
+    /// Parses `a`, `t`, `n` or `pREGEX`.
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        match value {
+            "a" => Ok(Self::All),
+            "t" => Ok(Self::NonEmpty),
+            "n" => Ok(Self::None),
+            value => match value.strip_prefix('p') {
+                Some(regex) => Regex::new(regex)
+                    .map(Self::Matching)
+                    .map_err(|e| e.to_string()),
+                None => Err(format!("invalid numbering style: '{value}'")),
+            },
+        }
+    }
+}
+
+/// The logical page sections, each begun by a line of the section
+/// delimiter repeated three, two or one times.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Section {
+    Header,
+    Body,
+    Footer,
+}
+
+#[derive(Debug)]
+pub struct Config {
+    files: Vec<String>,
+    header: Style,
+    body: Style,
+    footer: Style,
+    delimiter: Vec<u8>,
+    start: i64,
+    increment: i64,
+    join_blank: u64,
+    renumber: bool,
+    number_format: NumberFormat,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    fn style(&self, section: Section) -> &Style {
+        match section {
+            Section::Header => &self.header,
+            Section::Body => &self.body,
+            Section::Footer => &self.footer,
+        }
+    }
+
+    /// The section `line` begins, if it is a section delimiter line.
+    fn section(&self, line: &[u8]) -> Option<Section> {
+        let len = self.delimiter.len();
+        if len == 0
+            || !line.len().is_multiple_of(len)
+            || !line.chunks(len).all(|c| c == self.delimiter)
+        {
+            return None;
+        }
+        match line.len() / len {
+            3 => Some(Section::Header),
+            2 => Some(Section::Body),
+            1 => Some(Section::Footer),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    header: Style,
+    body: Style,
+    footer: Style,
+    delimiter: Vec<u8>,
+    start: i64,
+    increment: i64,
+    join_blank: u64,
+    renumber: bool,
+    number_format: NumberFormat,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            header: Style::None,
+            body: Style::NonEmpty,
+            footer: Style::None,
+            delimiter: b"\\:".to_vec(),
+            start: 1,
+            increment: 1,
+            join_blank: 1,
+            renumber: true,
+            number_format: NumberFormat::default(),
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Which header lines to number, none by default.
+    pub fn header_numbering(mut self, style: Style) -> Self {
+        self.header = style;
+        self
+    }
+
+    /// Which body lines to number, those not empty by default.
+    pub fn body_numbering(mut self, style: Style) -> Self {
+        self.body = style;
+        self
+    }
+
+    /// Which footer lines to number, none by default.
+    pub fn footer_numbering(mut self, style: Style) -> Self {
+        self.footer = style;
+        self
+    }
+
+    /// The characters that make up section delimiter lines, `\:` by
+    /// default. A single character is followed by `:`, and an empty
+    /// delimiter turns sections off.
+    pub fn section_delimiter(mut self, delimiter: impl Into<Vec<u8>>) -> Self {
+        self.delimiter = delimiter.into();
+        if self.delimiter.len() == 1 {
+            self.delimiter.push(b':');
+        }
+        self
+    }
+
+    /// The number of the first line of each section.
+    pub fn start(mut self, start: i64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The step from one line number to the next.
+    pub fn increment(mut self, increment: i64) -> Self {
+        self.increment = increment;
+        self
+    }
+
+    /// With `All`, number only the last of each run of this many blank
+    /// lines.
+    pub fn join_blank_lines(mut self, join_blank: u64) -> Self {
+        self.join_blank = join_blank;
+        self
+    }
+
+    /// Start numbering again at each section, as by default.
+    pub fn renumber(mut self, renumber: bool) -> Self {
+        self.renumber = renumber;
+        self
+    }
+
+    /// How line numbers are written, `%6d\t` by default.
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.number_format.width == 0 {
+            return Err(Error::Usage(
+                "invalid line number field width: 0".to_string(),
+            ));
+        }
+        if self.join_blank == 0 {
+            return Err(Error::Usage(
+                "invalid line number of blank lines: 0".to_string(),
+            ));
+        }
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            header: self.header,
+            body: self.body,
+            footer: self.footer,
+            delimiter: self.delimiter,
+            start: self.start,
+            increment: self.increment,
+            join_blank: self.join_blank,
+            renumber: self.renumber,
+            number_format: self.number_format,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("nlr")
+        .version("0.1.0")
+        .author("Talentaa <talentaa@qq.com>")
+        .about("Rust nl")
+        // -h is --header-numbering, as in nl.
+        .disable_help_flag(true)
+        .allow_negative_numbers(true)
+        .arg(
+            Arg::new("file")
+                .value_name("FILE")
+                .help("Input file(s)")
+                .num_args(1..)
+                .default_value("-"),
+        )
+        .arg(
+            Arg::new("body-numbering")
+                .short('b')
+                .long("body-numbering")
+                .value_name("STYLE")
+                .help("Body lines to number: a (all), t (nonempty), n (none) or pREGEX")
+                .value_parser(Style::parse)
+                .default_value("t"),
+        )
+        .arg(
+            Arg::new("section-delimiter")
+                .short('d')
+                .long("section-delimiter")
+                .value_name("CC")
+                .help("Characters of section delimiter lines")
+                .default_value("\\:"),
+        )
+        .arg(
+            Arg::new("footer-numbering")
+                .short('f')
+                .long("footer-numbering")
+                .value_name("STYLE")
+                .help("Footer lines to number, as for -b")
+                .value_parser(Style::parse)
+                .default_value("n"),
+        )
+        .arg(
+            Arg::new("header-numbering")
+                .short('h')
+                .long("header-numbering")
+                .value_name("STYLE")
+                .help("Header lines to number, as for -b")
+                .value_parser(Style::parse)
+                .default_value("n"),
+        )
+        .arg(
+            Arg::new("line-increment")
+                .short('i')
+                .long("line-increment")
+                .value_name("NUMBER")
+                .help("Step between line numbers")
+                .value_parser(clap::value_parser!(i64))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("join-blank-lines")
+                .short('l')
+                .long("join-blank-lines")
+                .value_name("NUMBER")
+                .help("Count this many blank lines as one with -b a")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("number-format")
+                .short('n')
+                .long("number-format")
+                .value_name("FORMAT")
+                .help("Line number justification")
+                .value_parser(clap::value_parser!(Justify))
+                .default_value("rn"),
+        )
+        .arg(
+            Arg::new("no-renumber")
+                .short('p')
+                .long("no-renumber")
+                .action(ArgAction::SetTrue)
+                .help("Do not reset line numbers for each section"),
+        )
+        .arg(
+            Arg::new("number-separator")
+                .short('s')
+                .long("number-separator")
+                .value_name("STRING")
+                .help("Add STRING after each line number")
+                .allow_hyphen_values(true)
+                .default_value("\t"),
+        )
+        .arg(
+            Arg::new("starting-line-number")
+                .short('v')
+                .long("starting-line-number")
+                .value_name("NUMBER")
+                .help("First line number of each section")
+                .value_parser(clap::value_parser!(i64))
+                .default_value("1"),
+        )
+        .arg(
+            Arg::new("number-width")
+                .short('w')
+                .long("number-width")
+                .value_name("NUMBER")
+                .help("Width of line numbers")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .default_value("6"),
+        )
+        .arg(
+            Arg::new("help")
+                .long("help")
+                .action(ArgAction::Help)
+                .help("Print help"),
+        )
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+    let style = |id| {
+        matches
+            .get_one::<Style>(id)
+            .cloned()
+            .expect("style defaulted")
+    };
+    let number = |id| *matches.get_one::<i64>(id).expect("number defaulted");
+    let value = |id| matches.get_one::<String>(id).expect("value defaulted");
+
+    Config::builder()
+        .files(
+            matches
+                .get_many::<String>("file")
+                .expect("files required")
+                .cloned(),
+        )
+        .header_numbering(style("header-numbering"))
+        .body_numbering(style("body-numbering"))
+        .footer_numbering(style("footer-numbering"))
+        .section_delimiter(value("section-delimiter").as_bytes())
+        .start(number("starting-line-number"))
+        .increment(number("line-increment"))
+        .join_blank_lines(*matches.get_one("join-blank-lines").expect("defaulted"))
+        .renumber(!matches.get_flag("no-renumber"))
+        .number_format(NumberFormat {
+            justify: *matches.get_one("number-format").expect("defaulted"),
+            width: *matches.get_one::<u64>("number-width").expect("defaulted") as usize,
+            separator: value("number-separator").as_bytes().to_vec(),
+        })
+        .build()
+}
+
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::open, common::stdout())
+}
+
+/// Numbers the lines of `config`'s files, opened through `inputs`, onto
+/// `out`. The files are numbered as one, as `nl` does.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, mut out: impl Write) -> Result<()> {
+    let mut failures = Failures::default();
+    let mut page = Page {
+        section: Section::Body,
+        number: Some(config.start),
+        blanks: 0,
+    };
+    for filename in &config.files {
+        match nl_file(filename, config, &mut inputs, &mut page, &mut out) {
+            Ok(ControlFlow::Continue(())) => {}
+            Ok(ControlFlow::Break(())) => {
+                failures.fail("line number overflow");
+                break;
+            }
+            Err(err) => failures.report(err)?,
+        }
+    }
+    out.flush()?;
+    failures.finish()
+}
+
+/// Where numbering stands, carried from one file to the next.
+#[derive(Debug)]
+struct Page {
+    section: Section,
+    /// The next line number, or `None` once it has overflowed.
+    number: Option<i64>,
+    /// Blank lines seen since the last one numbered, for `-l`.
+    blanks: u64,
+}
+
+/// Numbers the lines of `filename`, breaking off once the line numbers
+/// overflow, after which nothing more can be numbered.
+fn nl_file(
+    filename: &str,
+    config: &Config,
+    inputs: &mut impl Inputs,
+    page: &mut Page,
+    out: &mut impl Write,
+) -> Result<ControlFlow<()>> {
+    let file = inputs
+        .open(filename)
+        .map_err(|e| Error::open(filename, e))?;
+    let mut records = Records::new(file, b'\n');
+    let mut line = Vec::new();
+    while records
+        .read(&mut line)
+        .map_err(|e| Error::read(filename, e))?
+    {
+        let text = records.strip(&line);
+        if let Some(section) = config.section(text) {
+            page.section = section;
+            if config.renumber {
+                page.number = Some(config.start);
+            }
+            out.write_all(b"\n")?;
+            continue;
+        }
+
+        let numbered = match config.style(page.section) {
+            Style::All if text.is_empty() => {
+                page.blanks += 1;
+                page.blanks >= config.join_blank
+            }
+            Style::All => true,
+            Style::NonEmpty => !text.is_empty(),
+            Style::None => false,
+            Style::Matching(regex) => regex.is_match(text),
+        };
+        if numbered {
+            page.blanks = 0;
+            let Some(number) = page.number else {
+                return Ok(ControlFlow::Break(()));
+            };
+            config.number_format.write(number, out)?;
+            page.number = number.checked_add(config.increment);
+        } else {
+            config.number_format.write_blank(out)?;
+        }
+        out.write_all(text)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config, Style};
+    use common::{Justify, NumberFormat};
+    use std::io::{self, BufRead, Cursor};
+
+    const PAGES: &str = "\\:\\:\\:\nhead\n\\:\\:\none\n\ntwo\n\\:\nfoot\n";
+
+    fn run(config: Config, input: &'static str) -> String {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> { Ok(Box::new(Cursor::new(input))) };
+        let mut out = Vec::new();
+        run_with(&config, inputs, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_parse_style() {
+        assert!(matches!(Style::parse("a"), Ok(Style::All)));
+        assert!(matches!(Style::parse("t"), Ok(Style::NonEmpty)));
+        assert!(matches!(Style::parse("n"), Ok(Style::None)));
+        assert!(matches!(Style::parse("p^#"), Ok(Style::Matching(_))));
+        assert!(Style::parse("p(").is_err());
+        assert!(Style::parse("x").is_err());
+    }
+
+    #[test]
+    fn test_sections() {
+        let config = Config::builder().build().unwrap();
+        assert_eq!(
+            run(config, PAGES),
+            "\n       head\n\n     1\tone\n       \n     2\ttwo\n\n       foot\n"
+        );
+
+        let config = Config::builder()
+            .header_numbering(Style::All)
+            .footer_numbering(Style::NonEmpty)
+            .renumber(false)
+            .build()
+            .unwrap();
+        assert_eq!(
+            run(config, PAGES),
+            "\n     1\thead\n\n     2\tone\n       \n     3\ttwo\n\n     4\tfoot\n"
+        );
+
+        let config = Config::builder().section_delimiter("@").build().unwrap();
+        assert_eq!(run(config, "@:@:\n\\:\n"), "\n     1\t\\:\n");
+        let config = Config::builder().section_delimiter("").build().unwrap();
+        assert_eq!(run(config, "\\:\n"), "     1\t\\:\n");
+    }
+
+    #[test]
+    fn test_numbering() {
+        let config = Config::builder()
+            .body_numbering(Style::All)
+            .join_blank_lines(2)
+            .start(-1)
+            .increment(2)
+            .number_format(NumberFormat {
+                justify: Justify::RightZero,
+                width: 3,
+                separator: b": ".to_vec(),
+            })
+            .build()
+            .unwrap();
+        assert_eq!(
+            run(config, "a\n\n\n\nb"),
+            "-01: a\n     \n001: \n     \n003: b\n"
+        );
+
+        let config = Config::builder()
+            .body_numbering(Style::parse("p^#").unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(
+            run(config, "# a\nb\n#c\n"),
+            "     1\t# a\n       b\n     2\t#c\n"
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let config = Config::builder()
+            .body_numbering(Style::All)
+            .start(i64::MAX)
+            .build()
+            .unwrap();
+        let inputs =
+            |_: &str| -> io::Result<Box<dyn BufRead>> { Ok(Box::new(Cursor::new("a\nb\n"))) };
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_err());
+        assert_eq!(out, format!("{}\ta\n", i64::MAX).as_bytes());
+    }
+
+    #[test]
+    fn test_build() {
+        let format = NumberFormat {
+            width: 0,
+            ..NumberFormat::default()
+        };
+        assert!(Config::builder().number_format(format).build().is_err());
+        assert!(Config::builder().join_blank_lines(0).build().is_err());
+    }
+}
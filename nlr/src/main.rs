@@ -0,0 +1,5 @@
+fn main() {
+    if let Err(e) = nlr::get_args().and_then(nlr::run) {
+        e.exit();
+    }
+}
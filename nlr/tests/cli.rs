@@ -0,0 +1,223 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use std::fs;
+
+const PRG: &str = "nlr";
+const EMPTY: &str = "tests/inputs/empty.txt";
+const FOX: &str = "tests/inputs/fox.txt";
+const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const BLANKS: &str = "tests/inputs/blanks.txt";
+const PAGES: &str = "tests/inputs/pages.txt";
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+fn gen_bad_file() -> String {
+    loop {
+        let filename: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(7)
+            .map(char::from)
+            .collect();
+
+        if fs::metadata(&filename).is_err() {
+            return filename;
+        }
+    }
+}
+
+fn run(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+fn run_stdin(input: impl Into<Vec<u8>>, args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn skips_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("{}: .*[(]os error 2[)]", bad);
+    Command::cargo_bin(PRG)?
+        .args([&bad, FOX])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(fs::read_to_string("tests/expected/fox.txt.out")?)
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, [BUSTLE; 2000])
+}
+
+#[test]
+fn dies_bad_style() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-b", "x", FOX])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("invalid numbering style"));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_width() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "0", FOX])
+        .assert()
+        .failure()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn dies_overflow() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-v", &i64::MAX.to_string()])
+        .write_stdin("a\nb\n")
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(format!("{}\ta\n", i64::MAX))
+        .stderr("line number overflow\n");
+    Ok(())
+}
+
+#[test]
+fn empty() -> TestResult {
+    run(&[EMPTY], "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn fox() -> TestResult {
+    run(&[FOX], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn fox_ba() -> TestResult {
+    run(&["-ba", FOX], "tests/expected/fox.txt.ba.out")
+}
+
+#[test]
+fn bustle() -> TestResult {
+    run(&[BUSTLE], "tests/expected/the-bustle.txt.out")
+}
+
+#[test]
+fn bustle_ba() -> TestResult {
+    run(&["-b", "a", BUSTLE], "tests/expected/the-bustle.txt.ba.out")
+}
+
+#[test]
+fn bustle_stdin() -> TestResult {
+    run_stdin(
+        fs::read(BUSTLE)?,
+        &["-"],
+        "tests/expected/the-bustle.txt.stdin.out",
+    )
+}
+
+#[test]
+fn bustle_left() -> TestResult {
+    run(
+        &["-nln", "-w3", "-s", " | ", BUSTLE],
+        "tests/expected/the-bustle.txt.ln.out",
+    )
+}
+
+#[test]
+fn bustle_zeros_counting_down() -> TestResult {
+    run(
+        &["--number-format=rz", "-v", "10", "-i", "-2", BUSTLE],
+        "tests/expected/the-bustle.txt.rz.out",
+    )
+}
+
+#[test]
+fn all() -> TestResult {
+    run(&[EMPTY, FOX, BUSTLE], "tests/expected/all.out")
+}
+
+#[test]
+fn blanks() -> TestResult {
+    run(&[BLANKS], "tests/expected/blanks.txt.out")
+}
+
+#[test]
+fn blanks_ba() -> TestResult {
+    run(&["-ba", BLANKS], "tests/expected/blanks.txt.ba.out")
+}
+
+#[test]
+fn blanks_joined() -> TestResult {
+    run(
+        &["-ba", "-l", "2", BLANKS],
+        "tests/expected/blanks.txt.ba.l2.out",
+    )
+}
+
+#[test]
+fn pages() -> TestResult {
+    run(&[PAGES], "tests/expected/pages.txt.out")
+}
+
+#[test]
+fn pages_ba() -> TestResult {
+    run(&["-ba", PAGES], "tests/expected/pages.txt.ba.out")
+}
+
+#[test]
+fn pages_header_footer() -> TestResult {
+    run(&["-ha", "-ft", PAGES], "tests/expected/pages.txt.ha.ft.out")
+}
+
+#[test]
+fn pages_no_renumber() -> TestResult {
+    run(&["-p", PAGES], "tests/expected/pages.txt.p.out")
+}
+
+#[test]
+fn pages_regex() -> TestResult {
+    run(&["-bp^#", PAGES], "tests/expected/pages.txt.regex.out")
+}
+
+#[test]
+fn pages_no_sections() -> TestResult {
+    run(
+        &["-d", "", PAGES],
+        "tests/expected/pages.txt.no-sections.out",
+    )
+}
+
+#[test]
+fn pages_delimiter() -> TestResult {
+    let input = fs::read_to_string(PAGES)?.replace("\\:", "@:");
+    run_stdin(input, &["-d@"], "tests/expected/pages.txt.d.out")
+}
+
+#[test]
+fn help_is_long_only() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("--help")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--header-numbering"));
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{value_parser, Arg, ArgAction, Command};
+use common::{Error, Failures, Result};
+
+type Entry = fn(Vec<OsString>) -> Result<()>;
+
+const TOOLS: &[(&str, Entry)] = &[
+    ("catr", |args| catr::get_args_from(args).and_then(catr::run)),
+    ("cutr", |args| cutr::get_args_from(args).and_then(cutr::run)),
+    ("echor", |args| {
+        echor::get_args_from(args).and_then(echor::run)
+    }),
+    ("findr", |args| {
+        findr::get_args_from(args).and_then(findr::run)
+    }),
+    ("headr", |args| {
+        headr::get_args_from(args).and_then(headr::run)
+    }),
+    ("nlr", |args| nlr::get_args_from(args).and_then(nlr::run)),
+    ("tacr", |args| tacr::get_args_from(args).and_then(tacr::run)),
+    ("uniqr", |args| {
+        uniqr::get_args_from(args).and_then(uniqr::run)
+    }),
+    ("wcr", |args| wcr::get_args_from(args).and_then(wcr::run)),
+];
+
+#[derive(Debug)]
+pub enum Config {
+    /// Run a tool; the arguments start with the tool's name.
+    Run(Vec<OsString>),
+    /// Create a symlink to this binary for every tool in a directory.
+    Install(PathBuf),
+    List,
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("rutils")
+        .version("0.1.0")
+        .author("Talentaa <talentaa@qq.com>")
+        .about("Rust coreutils in a single binary")
+        .after_help(format!("Tools: {}", tool_names().join(", ")))
+        .arg_required_else_help(true)
+        .allow_external_subcommands(true)
+        .external_subcommand_value_parser(value_parser!(OsString))
+        .arg(
+            Arg::new("install")
+                .long("install")
+                .value_name("DIR")
+                .value_parser(value_parser!(PathBuf))
+                .conflicts_with("list")
+                .help("Create a symlink for each tool in DIR"),
+        )
+        .arg(
+            Arg::new("list")
+                .long("list")
+                .action(ArgAction::SetTrue)
+                .help("List the available tools"),
+        )
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+
+    // Invoked through a symlink such as `catr -> rutils`
+    if let Some(name) = args.first().and_then(|arg0| tool_name(arg0)) {
+        if find_tool(name).is_some() {
+            return Ok(Config::Run(args));
+        }
+    }
+
+    let matches = common::get_matches_from(command(), args)?;
+
+    if let Some(dir) = matches.get_one::<PathBuf>("install") {
+        return Ok(Config::Install(dir.clone()));
+    }
+    if matches.get_flag("list") {
+        return Ok(Config::List);
+    }
+
+    match matches.subcommand() {
+        Some((name, sub_matches)) if find_tool(name).is_some() => {
+            let mut args = vec![OsString::from(name)];
+            args.extend(
+                sub_matches
+                    .get_many::<OsString>("")
+                    .unwrap_or_default()
+                    .cloned(),
+            );
+            Ok(Config::Run(args))
+        }
+        Some((name, _)) => Err(Error::Usage(format!(
+            "unknown tool '{}', expected one of: {}",
+            name,
+            tool_names().join(", ")
+        ))),
+        None => Err(Error::Usage("a tool name is required".to_string())),
+    }
+}
+
+pub fn run(config: Config) -> Result<()> {
+    match config {
+        Config::Run(args) => {
+            let entry = args
+                .first()
+                .and_then(|arg0| tool_name(arg0))
+                .and_then(find_tool)
+                .expect("tool checked in get_args");
+            entry(args)
+        }
+        Config::Install(dir) => install(&dir),
+        Config::List => {
+            let mut out = common::stdout();
+            for name in tool_names() {
+                writeln!(out, "{}", name)?;
+            }
+            out.flush()?;
+            Ok(())
+        }
+    }
+}
+
+fn install(dir: &Path) -> Result<()> {
+    let exe = env::current_exe().map_err(|e| Error::open("rutils", e))?;
+    let mut failures = Failures::default();
+    for name in tool_names() {
+        let link = dir.join(name);
+        if let Err(e) = symlink(&exe, &link) {
+            failures.report(Error::open(&link.display().to_string(), e))?;
+        }
+    }
+    failures.finish()
+}
+
+#[cfg(unix)]
+fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(original, link)
+}
+
+#[cfg(windows)]
+fn symlink(original: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(original, link.with_extension("exe"))
+}
+
+fn tool_name(arg0: &OsStr) -> Option<&str> {
+    Path::new(arg0).file_stem().and_then(OsStr::to_str)
+}
+
+fn find_tool(name: &str) -> Option<Entry> {
+    TOOLS
+        .iter()
+        .find(|(tool, _)| *tool == name)
+        .map(|(_, entry)| *entry)
+}
+
+fn tool_names() -> Vec<&'static str> {
+    TOOLS.iter().map(|(name, _)| *name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_args_from, tool_name, Config};
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_tool_name() {
+        assert_eq!(tool_name(OsStr::new("catr")), Some("catr"));
+        assert_eq!(tool_name(OsStr::new("/usr/local/bin/wcr")), Some("wcr"));
+        assert_eq!(tool_name(OsStr::new("headr.exe")), Some("headr"));
+    }
+
+    #[test]
+    fn test_get_args_from() {
+        let res = get_args_from(["/bin/catr", "-n", "foo"]);
+        assert!(matches!(res, Ok(Config::Run(args)) if args == ["/bin/catr", "-n", "foo"]));
+
+        let res = get_args_from(["rutils", "wcr", "-l", "foo"]);
+        assert!(matches!(res, Ok(Config::Run(args)) if args == ["wcr", "-l", "foo"]));
+
+        let res = get_args_from(["rutils", "--list"]);
+        assert!(matches!(res, Ok(Config::List)));
+
+        let res = get_args_from(["rutils", "--install", "bin"]);
+        assert!(matches!(res, Ok(Config::Install(dir)) if dir.as_os_str() == "bin"));
+
+        let res = get_args_from(["rutils", "lsr"]);
+        assert!(res.is_err());
+        assert_eq!(res.unwrap_err().exit_code(), 2);
+    }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    if let Err(e) = rutils::get_args().and_then(rutils::run) {
+        e.exit();
+    }
+}
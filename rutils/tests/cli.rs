@@ -0,0 +1,129 @@
+use assert_cmd::{cargo::cargo_bin, Command};
+use predicates::prelude::*;
+use std::{error::Error, fs};
+
+type TestResult = Result<(), Box<dyn Error>>;
+
+const PRG: &str = "rutils";
+const FOX: &str = "../catr/tests/inputs/fox.txt";
+
+// --------------------------------------------------
+#[test]
+fn dies_no_args() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Usage"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_tool() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["lsr", "-l"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("unknown tool 'lsr'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn lists_tools() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("--list")
+        .assert()
+        .success()
+        .stdout("catr\ncutr\nechor\nfindr\nheadr\nnlr\ntacr\nuniqr\nwcr\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn runs_subcommand() -> TestResult {
+    let expected = fs::read_to_string("../catr/tests/expected/fox.txt.n.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["catr", "-n", FOX])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn subcommand_help_names_tool() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["wcr", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Usage: wcr"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn subcommand_exit_status() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["headr", "tests/does-not-exist"])
+        .assert()
+        .failure()
+        .code(1);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn installs_symlinks() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    Command::cargo_bin(PRG)?
+        .arg("--install")
+        .arg(dir.path())
+        .assert()
+        .success();
+
+    for name in [
+        "catr", "cutr", "echor", "findr", "headr", "nlr", "tacr", "uniqr", "wcr",
+    ] {
+        let link = dir.path().join(name);
+        assert_eq!(fs::read_link(&link)?, cargo_bin(PRG));
+    }
+
+    Command::new(dir.path().join("echor"))
+        .args(["Hello", "there"])
+        .assert()
+        .success()
+        .stdout("Hello there\n");
+
+    let expected = fs::read_to_string("../wcr/tests/expected/fox.txt.out")?;
+    Command::new(dir.path().join("wcr"))
+        .current_dir("../wcr")
+        .arg("tests/inputs/fox.txt")
+        .assert()
+        .success()
+        .stdout(expected);
+
+    // A second install finds the links already there
+    Command::cargo_bin(PRG)?
+        .arg("--install")
+        .arg(dir.path())
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("File exists"));
+    Ok(())
+}
+
+#[test]
+fn subcommand_generates_man_page() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["headr", "--generate", "man"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(".TH headr"));
+    Ok(())
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+
+set -u
+
+ROOT="tests/inputs"
+OUT_DIR="tests/expected"
+
+[[ ! -d "$OUT_DIR" ]] && mkdir -p "$OUT_DIR"
+
+EMPTY="$ROOT/empty.txt"
+FOX="$ROOT/fox.txt"
+SPIDERS="$ROOT/spiders.txt"
+BUSTLE="$ROOT/the-bustle.txt"
+LOG="$ROOT/log.txt"
+ALL="$EMPTY $FOX $SPIDERS $BUSTLE"
+
+for FILE in $ALL $LOG; do
+    BASENAME=$(basename "$FILE")
+    tac    $FILE > ${OUT_DIR}/${BASENAME}.out
+    tac -b $FILE > ${OUT_DIR}/${BASENAME}.b.out
+done
+
+tac $ALL > $OUT_DIR/all.out
+tac < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).stdin.out
+
+tac -s ' ' $SPIDERS > $OUT_DIR/spiders.txt.space.out
+tac -r -s '^2024-' -b $LOG > $OUT_DIR/log.txt.regex.out
+tac -r -s '[0-9]+' $LOG > $OUT_DIR/log.txt.digits.out
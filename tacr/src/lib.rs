@@ -0,0 +1,378 @@
+mod separator;
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::File,
+    io::{self, BufRead, Read, Seek, SeekFrom, Write},
+};
+
+use clap::{Arg, ArgAction, Command};
+use common::{Error, Failures, Inputs, Result};
+use separator::Separator;
+
+/// The most read at once when reading a file backwards, unless a record
+/// longer than this is still being looked at.
+const BLOCK_SIZE: usize = 128 * 1024;
+
+#[derive(Debug)]
+pub struct Config {
+    files: Vec<String>,
+    separator: Separator,
+    before: bool,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    separator: String,
+    regex: bool,
+    before: bool,
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> Self {
+        Self {
+            files: vec![],
+            separator: "\n".to_string(),
+            regex: false,
+            before: false,
+        }
+    }
+}
+
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// What records end with, newline by default.
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Take the separator as a regex.
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.regex = regex;
+        self
+    }
+
+    /// Attach the separator to the start of the record after it rather than
+    /// the end of the one before.
+    pub fn before(mut self, before: bool) -> Self {
+        self.before = before;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        if self.separator.is_empty() {
+            return Err(Error::Usage("separator cannot be empty".to_string()));
+        }
+        let separator = if self.regex {
+            Separator::regex(&self.separator).map_err(Error::Usage)?
+        } else {
+            Separator::String(self.separator.into_bytes())
+        };
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            separator,
+            before: self.before,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("tacr")
+        .version("0.1.0")
+        .author("Talentaa <talentaa@qq.com>")
+        .about("Rust tac")
+        .arg(
+            Arg::new("file")
+                .value_name("FILE")
+                .help("Input file(s)")
+                .num_args(1..)
+                .default_value("-"),
+        )
+        .arg(
+            Arg::new("before")
+                .short('b')
+                .long("before")
+                .action(ArgAction::SetTrue)
+                .help("Attach the separator before instead of after"),
+        )
+        .arg(
+            Arg::new("regex")
+                .short('r')
+                .long("regex")
+                .action(ArgAction::SetTrue)
+                .help("Interpret the separator as a regular expression"),
+        )
+        .arg(
+            Arg::new("separator")
+                .short('s')
+                .long("separator")
+                .value_name("STRING")
+                .help("Use STRING as the separator instead of newline")
+                .allow_hyphen_values(true)
+                .default_value("\n")
+                .hide_default_value(true),
+        )
+}
+
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
+
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
+
+    Config::builder()
+        .files(
+            matches
+                .get_many::<String>("file")
+                .expect("files required")
+                .cloned(),
+        )
+        .separator(
+            matches
+                .get_one::<String>("separator")
+                .expect("separator defaulted"),
+        )
+        .regex(matches.get_flag("regex"))
+        .before(matches.get_flag("before"))
+        .build()
+}
+
+/// Where a file's bytes come from.
+enum Source {
+    /// A stream, read whole into memory.
+    Reader(Box<dyn BufRead>),
+    /// A regular file, read backwards from its end down to `start`.
+    File { file: File, start: u64 },
+}
+
+pub fn run(config: Config) -> Result<()> {
+    tac_files(&config, open, common::stdout())
+}
+
+/// Writes the records of each of `config`'s files, opened through
+/// `inputs`, onto `out` last first. Each file is read into memory whole;
+/// [`run`] instead reads regular files backwards from the end.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, out: impl Write) -> Result<()> {
+    tac_files(config, |name| inputs.open(name).map(Source::Reader), out)
+}
+
+/// Opens `filename` to be read backwards if it is an uncompressed regular
+/// file, and as a stream otherwise.
+#[cfg(unix)]
+fn open(filename: &str) -> io::Result<Source> {
+    match common::open_plain(filename)? {
+        common::Plain::File(mut reader) if reader.get_ref().metadata()?.is_file() => {
+            let start = reader.stream_position()?;
+            let file = reader.into_inner();
+            Ok(Source::File { file, start })
+        }
+        common::Plain::File(reader) => Ok(Source::Reader(Box::new(reader))),
+        common::Plain::Decoded(reader) => Ok(Source::Reader(reader)),
+    }
+}
+
+#[cfg(not(unix))]
+fn open(filename: &str) -> io::Result<Source> {
+    common::open(filename).map(Source::Reader)
+}
+
+fn tac_files(
+    config: &Config,
+    mut open: impl FnMut(&str) -> io::Result<Source>,
+    mut out: impl Write,
+) -> Result<()> {
+    let mut failures = Failures::default();
+    for filename in &config.files {
+        let result = open(filename)
+            .map_err(|e| Error::open(filename, e))
+            .and_then(|source| tac_file(filename, config, source, &mut out));
+        if let Err(err) = result {
+            failures.report(err)?;
+        }
+    }
+    out.flush()?;
+    failures.finish()
+}
+
+fn tac_file(filename: &str, config: &Config, source: Source, out: &mut impl Write) -> Result<()> {
+    let read_error = |e| Error::read(filename, e);
+    let (mut buf, mut backwards) = match source {
+        Source::Reader(mut reader) => {
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).map_err(read_error)?;
+            (buf, None)
+        }
+        Source::File { file, start } => {
+            let pos = file.metadata().map_err(read_error)?.len().max(start);
+            (Vec::new(), Some(Backwards { file, start, pos }))
+        }
+    };
+
+    // `buf` holds what has been read and not yet written; separators are
+    // looked for before `search_end`, where the last one found started.
+    let mut search_end = buf.len();
+    loop {
+        let at_start = backwards.as_ref().is_none_or(Backwards::at_start);
+        // A match at the very start of `buf` might run on into what comes
+        // before it, so it waits until that has been read.
+        while let Some(found) = config
+            .separator
+            .rfind(&buf[..search_end])
+            .filter(|found| found.start > 0 || at_start)
+        {
+            let record = if config.before {
+                found.start
+            } else {
+                found.end
+            };
+            out.write_all(&buf[record..])?;
+            buf.truncate(record);
+            search_end = found.start;
+        }
+        match backwards.as_mut() {
+            Some(backwards) if !at_start => {
+                search_end += backwards.prepend(&mut buf).map_err(read_error)?;
+            }
+            _ => break,
+        }
+    }
+    out.write_all(&buf)?;
+    Ok(())
+}
+
+/// A file being read from its end towards `start`.
+struct Backwards {
+    file: File,
+    start: u64,
+    /// Where the bytes read so far begin.
+    pos: u64,
+}
+
+impl Backwards {
+    fn at_start(&self) -> bool {
+        self.pos == self.start
+    }
+
+    /// Puts the bytes before those read so far in front of `buf`, and
+    /// returns how many there were. At least as many are read as `buf`
+    /// already holds, so a long record is not read a block at a time.
+    fn prepend(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let len = (self.pos - self.start).min(BLOCK_SIZE.max(buf.len()) as u64) as usize;
+        self.pos -= len as u64;
+        let mut bytes = vec![0; len + buf.len()];
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        self.file.read_exact(&mut bytes[..len])?;
+        bytes[len..].copy_from_slice(buf);
+        *buf = bytes;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_with, tac_file, Backwards, Config, Source, BLOCK_SIZE};
+    use std::{
+        fs::{self, File},
+        io::{self, BufRead, Cursor},
+    };
+
+    fn run(config: Config, input: &'static str) -> String {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> { Ok(Box::new(Cursor::new(input))) };
+        let mut out = Vec::new();
+        run_with(&config, inputs, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_run_with() {
+        let config = || Config::builder();
+        assert_eq!(run(config().build().unwrap(), "a\nb\nc\n"), "c\nb\na\n");
+        assert_eq!(run(config().build().unwrap(), "a\nb\nc"), "cb\na\n");
+        assert_eq!(run(config().build().unwrap(), ""), "");
+        assert_eq!(
+            run(config().before(true).build().unwrap(), "a\nb\nc\n"),
+            "\n\nc\nba"
+        );
+        assert_eq!(
+            run(config().separator(", ").build().unwrap(), "x, y, z"),
+            "zy, x, "
+        );
+        assert_eq!(
+            run(
+                config().separator("[0-9]+").regex(true).build().unwrap(),
+                "a1b22c333"
+            ),
+            "33c32b2a1"
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        assert!(Config::builder().separator("").build().is_err());
+        assert!(Config::builder()
+            .separator("(")
+            .regex(true)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_backwards() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines");
+        let lines: Vec<String> = (0..BLOCK_SIZE / 4).map(|i| format!("{i}\n")).collect();
+        let mut long = "x".repeat(3 * BLOCK_SIZE);
+        long.push('\n');
+        fs::write(&path, lines.concat() + &long + "last").unwrap();
+
+        let config = Config::builder().build().unwrap();
+        let file = File::open(&path).unwrap();
+        let pos = file.metadata().unwrap().len();
+        let mut out = Vec::new();
+        tac_file("lines", &config, Source::File { file, start: 0 }, &mut out).unwrap();
+        let mut expected = "last".to_string() + &long;
+        expected.extend(lines.iter().rev().map(String::as_str));
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        // Reading stops at `start`, as for stdin already part way through.
+        let mut backwards = Backwards {
+            file: File::open(&path).unwrap(),
+            start: pos - 4,
+            pos,
+        };
+        let mut buf = Vec::new();
+        assert_eq!(backwards.prepend(&mut buf).unwrap(), 4);
+        assert_eq!(buf, b"last");
+        assert!(backwards.at_start());
+    }
+}
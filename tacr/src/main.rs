@@ -0,0 +1,5 @@
+fn main() {
+    if let Err(e) = tacr::get_args().and_then(tacr::run) {
+        e.exit();
+    }
+}
@@ -0,0 +1,120 @@
+use std::ops::Range;
+
+use memchr::memmem;
+use regex::bytes::{Regex, RegexBuilder};
+
+/// How far back from its end a regex separator is first looked for; the
+/// window doubles each time nothing turns up.
+const WINDOW: usize = 128;
+
+/// What records end with, or begin with under `--before`.
+#[derive(Debug, Clone)]
+pub enum Separator {
+    String(Vec<u8>),
+    Regex(Regex),
+}
+
+impl Separator {
+    /// Compiles `pattern` as a regex separator, in which `^` and `$` match
+    /// at line breaks as in GNU tac. It must not match an empty string.
+    pub fn regex(pattern: &str) -> Result<Self, String> {
+        let regex = RegexBuilder::new(pattern)
+            .multi_line(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        if regex.is_match(b"") {
+            return Err(format!("separator '{pattern}' matches an empty string"));
+        }
+        Ok(Self::Regex(regex))
+    }
+
+    /// The match starting furthest along `haystack`, searching backwards as
+    /// GNU tac does. Of overlapping strings the last wins, and a regex
+    /// matches from the last place it can start.
+    pub fn rfind(&self, haystack: &[u8]) -> Option<Range<usize>> {
+        match self {
+            Self::String(string) => {
+                memmem::rfind(haystack, string).map(|start| start..start + string.len())
+            }
+            Self::Regex(regex) => {
+                let starting_at = |start| {
+                    regex
+                        .find_at(haystack, start)
+                        .filter(|m| m.start() == start && !m.is_empty())
+                        .map(|m| m.range())
+                };
+                // Positions from `end` on are known not to start a match.
+                let mut end = haystack.len();
+                while end > 0 {
+                    let from = end.saturating_sub(WINDOW.max(haystack.len() - end));
+                    // A match starting after the last one found going forwards
+                    // would have been found instead, so the last place one
+                    // starts is within that one.
+                    let mut last = None;
+                    let mut at = from;
+                    while at < end {
+                        match regex.find_at(haystack, at) {
+                            Some(m) if m.start() < end => {
+                                last = Some(m.range());
+                                at = m.end() + usize::from(m.is_empty());
+                            }
+                            _ => break,
+                        }
+                    }
+                    if let Some(found) = last.and_then(|last| last.rev().find_map(starting_at)) {
+                        return Some(found);
+                    }
+                    end = from;
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Separator, WINDOW};
+
+    #[test]
+    fn test_rfind_string() {
+        let newline = Separator::String(b"\n".to_vec());
+        assert_eq!(newline.rfind(b"a\nb\nc"), Some(3..4));
+        assert_eq!(newline.rfind(b"abc"), None);
+        let double = Separator::String(b"aa".to_vec());
+        assert_eq!(double.rfind(b"xaaay"), Some(2..4));
+    }
+
+    #[test]
+    fn test_rfind_regex() {
+        let digits = Separator::regex("[0-9]+").unwrap();
+        assert_eq!(digits.rfind(b"a1b22c333"), Some(8..9));
+        assert_eq!(digits.rfind(b"a1b22c"), Some(4..5));
+        assert_eq!(digits.rfind(b"abc"), None);
+
+        let mut long = b"x1".to_vec();
+        long.extend(vec![b'y'; 10_000]);
+        assert_eq!(digits.rfind(&long), Some(1..2));
+
+        let words = Separator::regex(r"--+\n").unwrap();
+        assert_eq!(words.rfind(b"a\n---\nb\n--\n"), Some(8..11));
+
+        // A match starting before the window and ending in it.
+        let mut across = vec![b'y'; WINDOW - 1];
+        across.extend(b"ab");
+        across.extend(vec![b'y'; WINDOW - 1]);
+        assert_eq!(
+            Separator::regex("ab").unwrap().rfind(&across),
+            Some(WINDOW - 1..WINDOW + 1)
+        );
+
+        let lines = Separator::regex("^x").unwrap();
+        assert_eq!(lines.rfind(b"x1\nx2 x3"), Some(3..4));
+    }
+
+    #[test]
+    fn test_regex_errors() {
+        assert!(Separator::regex("(").is_err());
+        assert!(Separator::regex("x*").is_err());
+    }
+}
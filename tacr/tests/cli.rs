@@ -0,0 +1,196 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use std::fs;
+
+const PRG: &str = "tacr";
+const EMPTY: &str = "tests/inputs/empty.txt";
+const FOX: &str = "tests/inputs/fox.txt";
+const SPIDERS: &str = "tests/inputs/spiders.txt";
+const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const LOG: &str = "tests/inputs/log.txt";
+
+type TestResult = Result<(), Box<dyn std::error::Error>>;
+
+fn gen_bad_file() -> String {
+    loop {
+        let filename: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(7)
+            .map(char::from)
+            .collect();
+
+        if fs::metadata(&filename).is_err() {
+            return filename;
+        }
+    }
+}
+
+fn run(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn skips_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = format!("{}: .*[(]os error 2[)]", bad);
+    Command::cargo_bin(PRG)?
+        .args([&bad, FOX])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(fs::read_to_string("tests/expected/fox.txt.out")?)
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, [BUSTLE; 2000])
+}
+
+#[test]
+fn dies_empty_separator() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-s", "", FOX])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr("separator cannot be empty\n");
+    Ok(())
+}
+
+#[test]
+fn dies_bad_regex() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-r", "-s", "(", FOX])
+        .assert()
+        .failure()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn empty() -> TestResult {
+    run(&[EMPTY], "tests/expected/empty.txt.out")
+}
+
+#[test]
+fn fox() -> TestResult {
+    run(&[FOX], "tests/expected/fox.txt.out")
+}
+
+#[test]
+fn spiders() -> TestResult {
+    run(&[SPIDERS], "tests/expected/spiders.txt.out")
+}
+
+#[test]
+fn spiders_b() -> TestResult {
+    run(&["-b", SPIDERS], "tests/expected/spiders.txt.b.out")
+}
+
+#[test]
+fn spiders_space() -> TestResult {
+    run(
+        &["-s", " ", SPIDERS],
+        "tests/expected/spiders.txt.space.out",
+    )
+}
+
+#[test]
+fn bustle() -> TestResult {
+    run(&[BUSTLE], "tests/expected/the-bustle.txt.out")
+}
+
+#[test]
+fn bustle_b() -> TestResult {
+    run(&["--before", BUSTLE], "tests/expected/the-bustle.txt.b.out")
+}
+
+#[test]
+fn bustle_stdin() -> TestResult {
+    let input = fs::read(BUSTLE)?;
+    let expected = fs::read("tests/expected/the-bustle.txt.stdin.out")?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+#[test]
+fn all() -> TestResult {
+    run(&[EMPTY, FOX, SPIDERS, BUSTLE], "tests/expected/all.out")
+}
+
+#[test]
+fn log() -> TestResult {
+    run(&[LOG], "tests/expected/log.txt.out")
+}
+
+#[test]
+fn log_b() -> TestResult {
+    run(&["-b", LOG], "tests/expected/log.txt.b.out")
+}
+
+#[test]
+fn log_regex() -> TestResult {
+    run(
+        &["-r", "-s", "^2024-", "-b", LOG],
+        "tests/expected/log.txt.regex.out",
+    )
+}
+
+#[test]
+fn log_digits() -> TestResult {
+    run(
+        &["--regex", "--separator", "[0-9]+", LOG],
+        "tests/expected/log.txt.digits.out",
+    )
+}
+
+#[test]
+fn large_file() -> TestResult {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("large.txt");
+    let lines: Vec<String> = (0..100_000)
+        .map(|i| format!("{i} {}\n", "-".repeat(i % 50)))
+        .collect();
+    fs::write(&path, lines.concat())?;
+    let expected: String = lines.iter().rev().map(String::as_str).collect();
+
+    Command::cargo_bin(PRG)?
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(expected.clone());
+    Command::cargo_bin(PRG)?
+        .write_stdin(lines.concat())
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let texts: Vec<_> = lines
+        .iter()
+        .map(|line| line.trim_end_matches('\n'))
+        .collect();
+    let mut expected = "\n".to_string();
+    for text in texts[1..].iter().rev() {
+        expected += &format!("\n{text}");
+    }
+    expected += texts[0];
+    let file = Command::cargo_bin(PRG)?
+        .args(["-b", "-r", "-s", r"\n"])
+        .arg(&path)
+        .output()?;
+    assert_eq!(String::from_utf8(file.stdout)?, expected);
+    Ok(())
+}
@@ -12,6 +12,7 @@ echo -ne "a\nb"      > $ROOT/t3.txt
 echo -ne "a\na\nb"   > $ROOT/t4.txt
 echo -ne "b\na\na\n" > $ROOT/t5.txt
 echo -ne "a\nb\nc\n" > $ROOT/t6.txt
+echo -ne "caf\xe9\ncaf\xe9\n\xff\xfe\n\xff\xfe\n\xff\xfd\n" > $ROOT/t7.txt
 
 for FILE in $ROOT/*.txt; do
     BASENAME=$(basename "$FILE")
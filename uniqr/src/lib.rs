@@ -1,21 +1,95 @@
 use std::{
+    borrow::Cow,
+    env,
+    ffi::OsString,
     fs::File,
-    io::{self, BufRead, BufReader, Write},
+    io::{BufWriter, Write},
 };
 
 use clap::{Arg, ArgAction, Command};
+use common::{Compat, Error, Format, Inputs, JsonWriter, Records, Result};
+use serde::Serialize;
 
 #[derive(Debug)]
 pub struct Config {
     in_file: String,
     out_file: Option<String>,
     count: bool,
+    terminator: u8,
+    format: Format,
+    compat: Option<Compat>,
 }
 
-type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    in_file: Option<String>,
+    out_file: Option<String>,
+    count: bool,
+    zero_terminated: bool,
+    format: Format,
+    compat: Option<Compat>,
+}
+
+impl ConfigBuilder {
+    /// Input name, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn in_file(mut self, in_file: impl Into<String>) -> Self {
+        self.in_file = Some(in_file.into());
+        self
+    }
+
+    /// File [`run`] writes to instead of stdout; unused by [`run_with`].
+    pub fn out_file(mut self, out_file: Option<String>) -> Self {
+        self.out_file = out_file;
+        self
+    }
+
+    pub fn count(mut self, count: bool) -> Self {
+        self.count = count;
+        self
+    }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("uniqr")
+    /// Compare records separated by NUL instead of lines.
+    pub fn zero_terminated(mut self, zero_terminated: bool) -> Self {
+        self.zero_terminated = zero_terminated;
+        self
+    }
+
+    /// Output format. JSON objects always carry the count.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compare whole records and write counts as another implementation
+    /// does. By default, trailing whitespace is ignored when comparing.
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        Ok(Config {
+            in_file: self.in_file.unwrap_or_else(|| "-".to_string()),
+            out_file: self.out_file,
+            count: self.count,
+            terminator: common::terminator(self.zero_terminated),
+            format: self.format,
+            compat: self.compat,
+        })
+    }
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("uniqr")
         .version("0.1.0")
         .author("Talentaa <talentaa@qq.com>")
         .about("Rust uniq")
@@ -37,61 +111,159 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Show counts"),
         )
-        .get_matches();
+        .arg(common::zero_terminated_arg())
+        .args(Format::args())
+        .arg(Compat::arg())
+}
 
-    Ok(Config {
-        in_file: matches.get_one("in_file").cloned().unwrap(),
-        out_file: matches.get_one("out_file").cloned(),
-        count: matches.get_flag("count"),
-    })
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    match open(&config.in_file) {
-        Err(e) => eprintln!("{}: {}", config.in_file, e),
-        Ok(mut file) => {
-            let mut line = String::new();
-            let mut previous = String::new();
-            let mut count: u64 = 0;
-
-            let mut out_file: Box<dyn Write> = match &config.out_file {
-                Some(out_name) => Box::new(File::create(out_name)?),
-                _ => Box::new(io::stdout()),
-            };
-
-            let mut print = |count: u64, text: &str| -> MyResult<()> {
-                if count > 0 {
-                    if config.count {
-                        write!(out_file, "{:>4} {}", count, text)?;
-                    } else {
-                        write!(out_file, "{}", text)?;
-                    }
-                }
-                Ok(())
-            };
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
 
-            while let Ok(bytes) = file.read_line(&mut line) {
-                if bytes == 0 {
-                    break;
-                }
-                if line.trim_end() != previous.trim_end() {
-                    print(count, &previous)?;
-                    (previous, line) = (line, previous);
-                    count = 0;
-                }
+    Config::builder()
+        .in_file(matches.get_one::<String>("in_file").cloned().unwrap())
+        .out_file(matches.get_one("out_file").cloned())
+        .count(matches.get_flag("count"))
+        .zero_terminated(matches.get_flag("zero_terminated"))
+        .format(Format::from_matches(&matches))
+        .compat(Compat::from_matches(&matches))
+        .build()
+}
 
-                count += 1;
-                line.clear();
+pub fn run(config: Config) -> Result<()> {
+    // The input is opened before OUT_FILE is created, so that a missing
+    // input leaves OUT_FILE as it was.
+    let mut in_file =
+        Some(common::open(&config.in_file).map_err(|e| Error::open(&config.in_file, e))?);
+    let out_file: Box<dyn Write> = match &config.out_file {
+        Some(out_name) => Box::new(BufWriter::new(
+            File::create(out_name).map_err(|e| Error::open(out_name, e))?,
+        )),
+        _ => common::stdout(),
+    };
+    let inputs = |_: &str| Ok(in_file.take().expect("input opened once"));
+    run_with(&config, inputs, out_file)
+}
+
+/// The JSON object for each run of duplicate lines.
+#[derive(Debug, Serialize)]
+struct Line<'a> {
+    line: Cow<'a, str>,
+    count: u64,
+}
+
+/// Collapses adjacent duplicate lines of `config`'s input, opened through
+/// `inputs`, onto `out_file`.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, mut out_file: impl Write) -> Result<()> {
+    let file = inputs
+        .open(&config.in_file)
+        .map_err(|e| Error::open(&config.in_file, e))?;
+    let mut records = Records::new(file, config.terminator);
+    let mut line = Vec::new();
+    let mut previous = Vec::new();
+    let mut count: u64 = 0;
+    let mut json = JsonWriter::new(config.format);
+
+    let mut print = |count: u64, text: &[u8]| -> Result<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        if config.format.is_text() {
+            if config.count {
+                match config.compat {
+                    None | Some(Compat::Bsd) => write!(out_file, "{:>4} ", count)?,
+                    Some(Compat::Gnu) => write!(out_file, "{:>7} ", count)?,
+                    Some(Compat::Posix) => write!(out_file, "{} ", count)?,
+                }
+            }
+            out_file.write_all(text)?;
+            if config.compat.is_some() && !text.ends_with(&[config.terminator]) {
+                out_file.write_all(&[config.terminator])?;
             }
+        } else {
+            let line =
+                String::from_utf8_lossy(text.strip_suffix(&[config.terminator]).unwrap_or(text));
+            json.write(&mut out_file, &Line { line, count })?;
+        }
+        Ok(())
+    };
+
+    while records
+        .read(&mut line)
+        .map_err(|e| Error::read(&config.in_file, e))?
+    {
+        let key = |record| match config.compat {
+            None => records.strip(record).trim_ascii_end(),
+            Some(_) => records.strip(record),
+        };
+        if key(&line) != key(&previous) {
             print(count, &previous)?;
+            (previous, line) = (line, previous);
+            count = 0;
         }
+
+        count += 1;
     }
+    print(count, &previous)?;
+    json.finish(&mut out_file)?;
+    out_file.flush()?;
     Ok(())
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+#[cfg(test)]
+mod tests {
+    use super::{run_with, Config};
+    use common::Format;
+    use std::io::{self, BufRead, Cursor};
+
+    fn inputs(_: &str) -> io::Result<Box<dyn BufRead>> {
+        Ok(Box::new(Cursor::new("a\na\nb\na\n")))
+    }
+
+    #[test]
+    fn test_run_with() {
+        let config = Config::builder().build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"a\nb\na\n");
+
+        let config = Config::builder().count(true).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"   2 a\n   1 b\n   1 a\n");
+    }
+
+    #[test]
+    fn test_run_with_zero_terminated() {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new("a\nb\0a\nb\0c\0c")))
+        };
+        let config = Config::builder()
+            .count(true)
+            .zero_terminated(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(out, b"   2 a\nb\0   2 c\0");
+    }
+
+    #[test]
+    fn test_run_with_json() {
+        let config = Config::builder().format(Format::Json).build().unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[\n{\"line\":\"a\",\"count\":2},\n{\"line\":\"b\",\"count\":1},\n\
+            {\"line\":\"a\",\"count\":1}\n]\n"
+        );
     }
 }
@@ -1,5 +1,6 @@
 use assert_cmd::Command;
-use std::fs;
+use predicates::prelude::*;
+use std::{fs, io::Write};
 use tempfile::NamedTempFile;
 
 type TestResult = Result<(), Box<dyn std::error::Error>>;
@@ -78,8 +79,82 @@ const T6: Test = Test {
     out_count: "tests/expected/t6.txt.c.out",
 };
 
+const T7: Test = Test {
+    input: "tests/inputs/t7.txt",
+    out: "tests/expected/t7.txt.out",
+    out_count: "tests/expected/t7.txt.c.out",
+};
+
+#[test]
+fn dies_bad_file() -> TestResult {
+    let bad = "tests/inputs/does-not-exist.txt";
+    let expected = format!("{}: .*[(]os error 2[)]", bad);
+    Command::cargo_bin(PRG)?
+        .arg(bad)
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+#[test]
+fn bad_file_keeps_outfile() -> TestResult {
+    let mut outfile = NamedTempFile::new()?;
+    writeln!(outfile, "keep")?;
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/does-not-exist.txt"])
+        .arg(outfile.path())
+        .assert()
+        .failure()
+        .code(1);
+    assert_eq!(fs::read_to_string(outfile.path())?, "keep\n");
+    Ok(())
+}
+
+#[test]
+fn closed_pipe() -> TestResult {
+    let mut input = NamedTempFile::new()?;
+    for i in 0..20000 {
+        writeln!(input, "line {}", i)?;
+    }
+    common::testing::run_closed_pipe(PRG, [input.path()])
+}
+
+#[test]
+fn json() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .arg("--json")
+        .write_stdin("a\na\nb\n")
+        .assert()
+        .success()
+        .stdout("[\n{\"line\":\"a\",\"count\":2},\n{\"line\":\"b\",\"count\":1}\n]\n");
+    Ok(())
+}
+
+#[test]
+fn zero_terminated() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-c"])
+        .write_stdin("a\nb\0a\nb\0c\0")
+        .assert()
+        .success()
+        .stdout(predicate::eq(&b"   2 a\nb\0   1 c\0"[..]));
+    Ok(())
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "powershell"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("'uniqr'"));
+    Ok(())
+}
+
 fn run(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out)?;
+    let expected = fs::read(test.out)?;
     Command::cargo_bin(PRG)?
         .arg(test.input)
         .assert()
@@ -89,7 +164,7 @@ fn run(test: &Test) -> TestResult {
 }
 
 fn run_count(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out_count)?;
+    let expected = fs::read(test.out_count)?;
     Command::cargo_bin(PRG)?
         .args([test.input, "-c"])
         .assert()
@@ -99,8 +174,8 @@ fn run_count(test: &Test) -> TestResult {
 }
 
 fn run_stdin(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
-    let expected = fs::read_to_string(test.out)?;
+    let input = fs::read(test.input)?;
+    let expected = fs::read(test.out)?;
     Command::cargo_bin(PRG)?
         .write_stdin(input)
         .assert()
@@ -110,8 +185,8 @@ fn run_stdin(test: &Test) -> TestResult {
 }
 
 fn run_stdin_count(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
-    let expected = fs::read_to_string(test.out_count)?;
+    let input = fs::read(test.input)?;
+    let expected = fs::read(test.out_count)?;
     Command::cargo_bin(PRG)?
         .arg("--count")
         .write_stdin(input)
@@ -122,7 +197,7 @@ fn run_stdin_count(test: &Test) -> TestResult {
 }
 
 fn run_outfile(test: &Test) -> TestResult {
-    let expected = fs::read_to_string(test.out)?;
+    let expected = fs::read(test.out)?;
     let outfile = NamedTempFile::new()?;
     let outpath = &outfile.path().to_str().unwrap();
     Command::cargo_bin(PRG)?
@@ -131,7 +206,7 @@ fn run_outfile(test: &Test) -> TestResult {
         .success()
         .stdout("");
 
-    let contents = fs::read_to_string(outpath)?;
+    let contents = fs::read(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -147,15 +222,15 @@ fn run_outfile_count(test: &Test) -> TestResult {
         .success()
         .stdout("");
 
-    let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(outpath)?;
+    let expected = fs::read(test.out_count)?;
+    let contents = fs::read(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
 }
 
 fn run_stdin_outfile_count(test: &Test) -> TestResult {
-    let input = fs::read_to_string(test.input)?;
+    let input = fs::read(test.input)?;
     let outfile = NamedTempFile::new()?;
     let outpath = &outfile.path().to_str().unwrap();
 
@@ -165,8 +240,8 @@ fn run_stdin_outfile_count(test: &Test) -> TestResult {
         .assert()
         .stdout("");
 
-    let expected = fs::read_to_string(test.out_count)?;
-    let contents = fs::read_to_string(outpath)?;
+    let expected = fs::read(test.out_count)?;
+    let contents = fs::read(outpath)?;
     assert_eq!(&expected, &contents);
 
     Ok(())
@@ -557,3 +632,37 @@ fn t6_stdin_outfile_count() -> TestResult {
     run_stdin_outfile_count(&T6)
 }
 
+#[test]
+fn t7() -> TestResult {
+    run(&T7)
+}
+
+#[test]
+fn t7_count() -> TestResult {
+    run_count(&T7)
+}
+
+#[test]
+fn t7_stdin() -> TestResult {
+    run_stdin(&T7)
+}
+
+#[test]
+fn t7_stdin_count() -> TestResult {
+    run_stdin_count(&T7)
+}
+
+#[test]
+fn t7_outfile() -> TestResult {
+    run_outfile(&T7)
+}
+
+#[test]
+fn t7_outfile_count() -> TestResult {
+    run_outfile_count(&T7)
+}
+
+#[test]
+fn t7_stdin_outfile_count() -> TestResult {
+    run_stdin_outfile_count(&T7)
+}
@@ -1,11 +1,13 @@
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, Write},
 };
 
 use clap::{Arg, ArgAction, Command};
-
-type MyResult<T> = Result<T, Box<dyn std::error::Error>>;
+use common::{Compat, Error, Failures, Format, Inputs, JsonWriter, Result};
+use serde::Serialize;
 
 #[derive(Debug)]
 pub struct Config {
@@ -14,18 +16,126 @@ pub struct Config {
     bytes: bool,
     words: bool,
     chars: bool,
+    format: Format,
+    compat: Option<Compat>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+}
+
+/// Selects the columns to print. With none selected, lines, words and bytes
+/// are printed, as with wc.
+#[derive(Debug, Default)]
+pub struct ConfigBuilder {
+    files: Vec<String>,
+    lines: bool,
+    bytes: bool,
+    words: bool,
+    chars: bool,
+    format: Format,
+    compat: Option<Compat>,
 }
 
-#[derive(Debug, PartialEq)]
+impl ConfigBuilder {
+    /// Input names, resolved through the `Inputs` given to [`run_with`].
+    /// Defaults to "-".
+    pub fn files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn lines(mut self, lines: bool) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    pub fn bytes(mut self, bytes: bool) -> Self {
+        self.bytes = bytes;
+        self
+    }
+
+    pub fn words(mut self, words: bool) -> Self {
+        self.words = words;
+        self
+    }
+
+    pub fn chars(mut self, chars: bool) -> Self {
+        self.chars = chars;
+        self
+    }
+
+    /// Output format. JSON objects always carry every count.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Align the counts as another implementation does.
+    pub fn compat(mut self, compat: Option<Compat>) -> Self {
+        self.compat = compat;
+        self
+    }
+
+    pub fn build(self) -> Result<Config> {
+        let (mut lines, mut bytes, mut words, chars) =
+            (self.lines, self.bytes, self.words, self.chars);
+
+        if bytes && chars {
+            return Err(Error::Usage(
+                "cannot count bytes and characters at once".to_string(),
+            ));
+        }
+        if [lines, bytes, words, chars].iter().all(|v| v == &false) {
+            (lines, bytes, words) = (true, true, true);
+        }
+
+        Ok(Config {
+            files: if self.files.is_empty() {
+                vec!["-".to_string()]
+            } else {
+                self.files
+            },
+            lines,
+            bytes,
+            words,
+            chars,
+            format: self.format,
+            compat: self.compat,
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
 pub struct FileInfo {
-    num_lines: usize,
-    num_words: usize,
-    num_bytes: usize,
-    num_chars: usize,
+    #[serde(rename = "lines")]
+    pub num_lines: usize,
+    #[serde(rename = "words")]
+    pub num_words: usize,
+    #[serde(rename = "bytes")]
+    pub num_bytes: usize,
+    #[serde(rename = "chars")]
+    pub num_chars: usize,
 }
 
-pub fn get_args() -> MyResult<Config> {
-    let matches = Command::new("wcr")
+/// The JSON object for one file, or for the total when `file` is null.
+#[derive(Debug, Serialize)]
+struct Counts<'a> {
+    file: Option<&'a str>,
+    #[serde(flatten)]
+    info: &'a FileInfo,
+}
+
+/// The command-line definition, also used to generate shell completions
+/// and man pages.
+pub fn command() -> Command {
+    Command::new("wcr")
         .version("0.1.0")
         .about("Rust wc")
         .author("Talentaa <talentaa@qq.com>")
@@ -65,81 +175,165 @@ pub fn get_args() -> MyResult<Config> {
                 .action(ArgAction::SetTrue)
                 .help("Print the word counts"),
         )
-        .get_matches();
+        .args(Format::args())
+        .arg(Compat::arg())
+}
 
-    let (mut lines, mut bytes, mut words, chars) = (
-        matches.get_flag("lines"),
-        matches.get_flag("bytes"),
-        matches.get_flag("words"),
-        matches.get_flag("chars"),
-    );
+pub fn get_args() -> Result<Config> {
+    get_args_from(env::args_os())
+}
 
-    if [lines, bytes, words, chars].iter().all(|v| v == &false) {
-        (lines, bytes, words) = (true, true, true);
-    }
+pub fn get_args_from<I, T>(args: I) -> Result<Config>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = common::get_matches_from(command(), args)?;
 
-    Ok(Config {
-        files: matches
-            .get_many("files")
-            .expect("files required")
-            .cloned()
-            .collect(),
-        lines,
-        bytes,
-        words,
-        chars,
-    })
+    Config::builder()
+        .files(
+            matches
+                .get_many::<String>("files")
+                .expect("files required")
+                .cloned(),
+        )
+        .lines(matches.get_flag("lines"))
+        .bytes(matches.get_flag("bytes"))
+        .words(matches.get_flag("words"))
+        .chars(matches.get_flag("chars"))
+        .format(Format::from_matches(&matches))
+        .compat(Compat::from_matches(&matches))
+        .build()
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
+pub fn run(config: Config) -> Result<()> {
+    run_with(&config, common::open, common::stdout())
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    let (mut total_lines, mut total_words, mut total_bytes, mut total_chars) = (0, 0, 0, 0);
+/// Writes the counts for each of `config`'s files, opened through `inputs`,
+/// to `out`.
+pub fn run_with(config: &Config, mut inputs: impl Inputs, mut out: impl Write) -> Result<()> {
+    let mut total = FileInfo::default();
+    let mut failures = Failures::default();
+    let mut json = JsonWriter::new(config.format);
+    let width = match config.compat {
+        Some(Compat::Gnu) => gnu_width(config),
+        _ => 1,
+    };
 
     for filename in &config.files {
-        match open(filename) {
-            Err(e) => eprintln!("{}: {}", filename, e),
-            Ok(file) => {
-                if let Ok(info) = count(file) {
-                    println!(
-                        "{}{}{}{}{}",
-                        format_field(info.num_lines, config.lines),
-                        format_field(info.num_words, config.words),
-                        format_field(info.num_bytes, config.bytes),
-                        format_field(info.num_chars, config.chars),
-                        if filename == "-" {
-                            "".to_string()
-                        } else {
-                            format!(" {}", filename)
-                        }
-                    );
-
-                    total_lines += info.num_lines;
-                    total_words += info.num_words;
-                    total_bytes += info.num_bytes;
-                    total_chars += info.num_chars;
+        match inputs.open(filename) {
+            Err(e) => failures.report(Error::open(filename, e))?,
+            Ok(file) => match count(file) {
+                Err(e) => failures.report(Error::read(filename, e))?,
+                Ok(info) => {
+                    if config.format.is_text() {
+                        print_counts(&mut out, config, &info, Some(filename), width)?;
+                    } else {
+                        let counts = Counts {
+                            file: Some(filename),
+                            info: &info,
+                        };
+                        json.write(&mut out, &counts)?;
+                    }
+
+                    total.num_lines += info.num_lines;
+                    total.num_words += info.num_words;
+                    total.num_bytes += info.num_bytes;
+                    total.num_chars += info.num_chars;
                 }
-            }
+            },
         }
     }
 
     if config.files.len() > 1 {
-        println!(
-            "{}{}{}{} total",
-            format_field(total_lines, config.lines),
-            format_field(total_words, config.words),
-            format_field(total_bytes, config.bytes),
-            format_field(total_chars, config.chars),
-        );
+        if config.format.is_text() {
+            print_counts(&mut out, config, &total, None, width)?;
+        } else {
+            let counts = Counts {
+                file: None,
+                info: &total,
+            };
+            json.write(&mut out, &counts)?;
+        }
     }
+    json.finish(&mut out)?;
+    out.flush()?;
+    failures.finish()
+}
+
+/// Writes one row of counts; `filename` is None for the total. `width` is
+/// the width of each count with `--compat gnu` or `posix`, which separate
+/// counts with a single space.
+fn print_counts(
+    out: &mut impl Write,
+    config: &Config,
+    info: &FileInfo,
+    filename: Option<&str>,
+    width: usize,
+) -> Result<()> {
+    let fields = [
+        (info.num_lines, config.lines),
+        (info.num_words, config.words),
+        (info.num_bytes, config.bytes),
+        (info.num_chars, config.chars),
+    ];
+    let counts = match config.compat {
+        None => fields
+            .map(|(value, show)| format_field(value, show))
+            .concat(),
+        Some(Compat::Bsd) => fields
+            .iter()
+            .filter(|(_, show)| *show)
+            .map(|(value, _)| format!(" {value:>7}"))
+            .collect(),
+        Some(Compat::Gnu | Compat::Posix) => fields
+            .iter()
+            .filter(|(_, show)| *show)
+            .map(|(value, _)| format!("{value:>width$}"))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    writeln!(
+        out,
+        "{}{}",
+        counts,
+        match filename {
+            None => " total".to_string(),
+            Some("-") => "".to_string(),
+            Some(name) => format!(" {}", name),
+        }
+    )?;
     Ok(())
 }
 
+/// The width GNU wc gives every count: the digits in the total size of the
+/// regular files, at least 7 if any input is not a regular file, and 1 for
+/// a single count of a single file. Files that cannot be read are ignored.
+fn gnu_width(config: &Config) -> usize {
+    let shown = [config.lines, config.words, config.bytes, config.chars]
+        .iter()
+        .filter(|show| **show)
+        .count();
+    if config.files.len() == 1 && shown == 1 {
+        return 1;
+    }
+
+    let stat = |filename: &String| match filename.as_str() {
+        "-" => fs::metadata("/dev/stdin"),
+        filename => fs::metadata(filename),
+    };
+    let (mut total, mut min_width) = (0, 1);
+    for metadata in config.files.iter().filter_map(|f| stat(f).ok()) {
+        if metadata.is_file() {
+            total += metadata.len();
+        } else {
+            min_width = 7;
+        }
+    }
+    total.to_string().len().max(min_width)
+}
+
 fn format_field(value: usize, show: bool) -> String {
     if show {
         format!("{value:>8}")
@@ -148,18 +342,31 @@ fn format_field(value: usize, show: bool) -> String {
     }
 }
 
-pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
+pub fn count(mut file: impl BufRead) -> io::Result<FileInfo> {
     let (mut num_lines, mut num_words, mut num_bytes, mut num_chars) = (0, 0, 0, 0);
-    let mut line = String::new();
+    let mut line = Vec::new();
 
-    while let Ok(line_bytes) = file.read_line(&mut line) {
+    loop {
+        let line_bytes = file.read_until(b'\n', &mut line)?;
         if line_bytes == 0 {
             break;
         }
-        num_lines += 1;
+        // Only newlines count as lines, so an unterminated last line does
+        // not, as with wc(1).
+        if line.ends_with(b"\n") {
+            num_lines += 1;
+        }
         num_bytes += line_bytes;
-        num_words += line.split_whitespace().count();
-        num_chars += line.chars().count();
+        // As with GNU wc, invalid bytes neither count as characters nor make
+        // up a word on their own.
+        num_words += String::from_utf8_lossy(&line)
+            .split_whitespace()
+            .filter(|word| word.chars().any(|c| c != char::REPLACEMENT_CHARACTER))
+            .count();
+        num_chars += line
+            .utf8_chunks()
+            .map(|chunk| chunk.valid().chars().count())
+            .sum::<usize>();
         line.clear();
     }
 
@@ -173,8 +380,9 @@ pub fn count(mut file: impl BufRead) -> MyResult<FileInfo> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{count, format_field, FileInfo};
-    use std::io::Cursor;
+    use crate::{count, format_field, run_with, Config, FileInfo};
+    use common::Format;
+    use std::io::{self, BufRead, Cursor};
 
     #[test]
     fn test_count() {
@@ -188,6 +396,24 @@ mod tests {
             num_bytes: 48,
         };
         assert_eq!(info.unwrap(), expected);
+
+        // Only newlines are counted as lines.
+        let info = count(Cursor::new("a\nb")).unwrap();
+        assert_eq!((info.num_lines, info.num_words), (1, 2));
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let text: &[u8] = b"caf\xe9 cr\xe8me\n\xff\xfe\n";
+        let info = count(Cursor::new(text));
+        assert!(info.is_ok());
+        let expected = FileInfo {
+            num_lines: 2,
+            num_words: 2,
+            num_chars: 10,
+            num_bytes: 14,
+        };
+        assert_eq!(info.unwrap(), expected);
     }
 
     #[test]
@@ -196,4 +422,51 @@ mod tests {
         assert_eq!(format_field(3, true), "       3");
         assert_eq!(format_field(10, true), "      10");
     }
+
+    #[test]
+    fn test_run_with() {
+        let inputs = |name: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new(format!("{name} one\n{name} two\n"))))
+        };
+        let config = Config::builder()
+            .files(["a", "b"])
+            .lines(true)
+            .words(true)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "       2       4 a\n       2       4 b\n       4       8 total\n"
+        );
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = Config::builder().build().unwrap();
+        assert!(config.lines && config.words && config.bytes && !config.chars);
+        assert_eq!(config.files, ["-"]);
+        assert!(Config::builder().bytes(true).chars(true).build().is_err());
+    }
+
+    #[test]
+    fn test_run_with_ndjson() {
+        let inputs = |_: &str| -> io::Result<Box<dyn BufRead>> {
+            Ok(Box::new(Cursor::new("caf\u{e9} one\n")))
+        };
+        let config = Config::builder()
+            .files(["a", "b"])
+            .format(Format::Ndjson)
+            .build()
+            .unwrap();
+        let mut out = Vec::new();
+        assert!(run_with(&config, inputs, &mut out).is_ok());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"file\":\"a\",\"lines\":1,\"words\":2,\"bytes\":10,\"chars\":9}\n\
+            {\"file\":\"b\",\"lines\":1,\"words\":2,\"bytes\":10,\"chars\":9}\n\
+            {\"file\":null,\"lines\":2,\"words\":4,\"bytes\":20,\"chars\":18}\n"
+        );
+    }
 }
@@ -1,7 +1,5 @@
 fn main() {
     if let Err(e) = wcr::get_args().and_then(wcr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+        e.exit();
     }
 }
-
@@ -8,7 +8,9 @@ type TestResult = Result<(), Box<dyn Error>>;
 const PRG: &str = "wcr";
 const EMPTY: &str = "tests/inputs/empty.txt";
 const ATLAMAL: &str = "tests/inputs/atlamal.txt";
+const ATLAMAL_ZST: &str = "tests/inputs/atlamal.txt.zst";
 const FOX: &str = "tests/inputs/fox.txt";
+const CONFIG: &str = "tests/config";
 
 fn run(args: &[&str], expected_file: &str) -> TestResult {
     let expected = fs::read_to_string(expected_file)?;
@@ -46,6 +48,98 @@ fn dies_chars_and_bytes() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn closed_pipe() -> TestResult {
+    common::testing::run_closed_pipe(PRG, [FOX; 2000])
+}
+
+#[test]
+fn config_file() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .args([FOX])
+        .assert()
+        .success()
+        .stdout(format!("       1 {FOX}\n"));
+    Ok(())
+}
+
+#[test]
+fn config_file_beneath_args() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .env("WCR_OPTS", "--chars")
+        .args(["-w", FOX])
+        .assert()
+        .success()
+        .stdout(format!("       1       9      48 {FOX}\n"));
+    Ok(())
+}
+
+#[test]
+fn no_config() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .env("WCR_OPTS", "--chars")
+        .args(["--no-config", FOX])
+        .assert()
+        .success()
+        .stdout(format!("       1       9      48 {FOX}\n"));
+    Ok(())
+}
+
+#[test]
+fn print_config() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("XDG_CONFIG_HOME", CONFIG)
+        .env("WCR_OPTS", "-c")
+        .args(["--print-config", "-w"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "bytes = true # environment\n\
+            chars = false # default\n\
+            lines = true # config file\n\
+            words = true # command line\n",
+        ));
+    Ok(())
+}
+
+#[test]
+fn dies_bad_opts() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .env("WCR_OPTS", "--bogus")
+        .arg(FOX)
+        .assert()
+        .failure()
+        .stderr("WCR_OPTS: unexpected argument '--bogus' found\n");
+    Ok(())
+}
+
+#[test]
+fn ndjson() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--ndjson", FOX, EMPTY])
+        .assert()
+        .success()
+        .stdout(
+            "{\"file\":\"tests/inputs/fox.txt\",\"lines\":1,\"words\":9,\"bytes\":48,\"chars\":48}\n\
+            {\"file\":\"tests/inputs/empty.txt\",\"lines\":0,\"words\":0,\"bytes\":0,\"chars\":0}\n\
+            {\"file\":null,\"lines\":1,\"words\":9,\"bytes\":48,\"chars\":48}\n",
+        );
+    Ok(())
+}
+
+#[test]
+fn generates_completions() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--generate", "elvish"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--chars"));
+    Ok(())
+}
+
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
@@ -53,7 +147,8 @@ fn skips_bad_file() -> TestResult {
     Command::cargo_bin(PRG)?
         .arg(bad)
         .assert()
-        .success()
+        .failure()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
@@ -149,6 +244,17 @@ fn atlamal_stdin() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn atlamal_zst_stdin() -> TestResult {
+    let input = fs::read(ATLAMAL_ZST)?;
+    let expected = fs::read_to_string("tests/expected/atlamal.txt.stdin.out")?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input)
+        .assert()
+        .stdout(expected);
+    Ok(())
+}
+
 #[test]
 fn test_all() -> TestResult {
     run(&[EMPTY, FOX, ATLAMAL], "tests/expected/all.out")